@@ -1,35 +1,49 @@
+//! `cargo mono bump`, which computes new versions of crates based on the
+//! published versions and patches manifests of the workspace.
+
 use std::{
     collections::HashMap,
-    fs::{read_to_string, write},
-    path::Path,
+    fs::{read_to_string, remove_file, write},
+    io::{self, IsTerminal},
+    path::{Path, PathBuf},
     sync::Arc,
 };
 
 use anyhow::{bail, Context, Result};
 use async_recursion::async_recursion;
 use cargo_metadata::Package;
-use clap::Args;
+use clap::{Args, ValueEnum};
 use requestty::{prompt_one, Answer, Question};
 use semver::Version;
-use tokio::{process::Command, task::spawn_blocking};
-use toml_edit::{Item, Value};
+use tokio::process::Command;
 use walkdir::WalkDir;
 
 use crate::{
-    cargo_workspace::fetch_ws_crates,
-    crates_io::{can_publish, fetch_published_version},
+    cargo_workspace::{match_crates, Workspace},
+    crates_io::can_publish,
+    filter::FilterArgs,
+    lockfile::update_lockfile,
+    manifest::{patch, patch_workspace_deps},
+    message::{Event, MessageFormat, PlannedVersion},
+    plan::{BumpPlan, PlannedBump},
+    registry::{
+        prefetch_published_versions, Fallback, GitTags, ManifestVersions, Registry, RegistryArgs,
+    },
+    requirements::{find_mismatched_requirements, fix_requirements},
 };
 
 /// Bump versions of a crate and dependant crates.
 ///
 /// The command ensures that the version is bumped compared to **the published
-/// version on crates.io**,
+/// version on crates.io**, or the source given by `--baseline`. Versions
+/// already bumped locally are considered only if `--local-version` is given.
 
 #[derive(Debug, Args)]
 pub struct BumpCommand {
-    /// Name of the crate to bump version
+    /// Names of the crates to bump version. Glob patterns like `swc_ecma_*` are
+    /// allowed.
     #[clap(name = "crate")]
-    pub crate_name: Option<String>,
+    pub crate_names: Vec<String>,
 
     /// Run in interactive mode
     #[clap(short = 'i', long)]
@@ -48,14 +62,86 @@ pub struct BumpCommand {
     /// Commit with the messahe `Bump version`.
     #[clap(short = 'g', long)]
     pub git: bool,
+
+    /// Save the computed plan to the file instead of modifying manifests.
+    ///
+    /// The plan can be applied later using `--plan`.
+    #[clap(long, value_name = "FILE", conflicts_with = "plan")]
+    pub save_plan: Option<PathBuf>,
+
+    /// Change requirements on workspace members to their local versions if
+    /// they don't match after bumping.
+    #[clap(long)]
+    pub fix_requirements: bool,
+
+    /// Crates to include or exclude.
+    #[clap(flatten)]
+    pub filter: FilterArgs,
+
+    /// Format of messages printed to stdout.
+    #[clap(long, value_enum, default_value_t)]
+    pub message_format: MessageFormat,
+
+    /// Source of the versions to bump from.
+    #[clap(long, value_enum, default_value_t)]
+    pub baseline: Baseline,
+
+    /// How to handle crates whose version in `Cargo.toml` is already ahead of
+    /// the baseline.
+    #[clap(long, value_enum, default_value_t)]
+    pub local_version: LocalVersion,
+
+    /// Registry to read published versions from.
+    #[clap(flatten)]
+    pub registry: RegistryArgs,
+
+    /// Apply a plan saved using `--save-plan` without prompting.
+    #[clap(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["crate", "interactive", "breaking", "with_dependants"]
+    )]
+    pub plan: Option<PathBuf>,
+}
+
+/// Source of the versions which `bump` increments.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Baseline {
+    /// The latest version published to the registry.
+    #[default]
+    Registry,
+    /// The latest version in git tags like `{name}@{version}` or
+    /// `{name}-v{version}`, for crates which are not published. The version in
+    /// `Cargo.toml` is used for crates without tags.
+    GitTags,
+    /// The version in `Cargo.toml`.
+    Manifest,
+}
+
+/// Handling of versions bumped locally but not published yet.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LocalVersion {
+    /// Bump from the baseline, even if the local version is ahead.
+    #[default]
+    Ignore,
+    /// Keep the local version if it's already same as or ahead of the bumped
+    /// version.
+    Skip,
+    /// Bump from the greater one of the local version and the baseline.
+    Max,
 }
 
 impl BumpCommand {
     fn get_crates_to_bump(&self, crates: &[Package]) -> Result<Vec<String>> {
-        if let Some(n) = &self.crate_name {
-            return Ok(vec![n.clone()]);
+        if !self.crate_names.is_empty() {
+            return match_crates(crates, &self.crate_names);
         }
 
+        ensure_terminal(
+            "Pass the name of the crate to bump (`cargo mono bump <crate>`) or apply a saved plan \
+             using `--plan <FILE>`.",
+        )?;
+
         let q = Question::multi_select("crates")
             .message("Select crates to bump version")
             .choices(crates.iter().map(|p| &*p.name));
@@ -73,129 +159,349 @@ impl BumpCommand {
         }
     }
 
+    /// Runs the command in the current workspace.
     pub async fn run(&self) -> Result<()> {
-        let workspace_crates = fetch_ws_crates().await?;
+        if self.interactive {
+            ensure_terminal(INTERACTIVE_HINT)?;
+        }
+
+        let ws = Workspace::load().await?;
+        let workspace_crates = &ws.members;
+
+        let registry: Box<dyn Registry> = match self.baseline {
+            Baseline::Registry => self.registry.build()?,
+            Baseline::GitTags => Box::new(Fallback {
+                primary: GitTags::new(&ws.root),
+                fallback: ManifestVersions::new(workspace_crates),
+            }),
+            Baseline::Manifest => Box::new(ManifestVersions::new(workspace_crates)),
+        };
+        let registry = &*registry;
+
+        let filter = self.filter.build(&ws.config)?;
+
+        // Crates which are not published to the registry don't have a version to bump
+        // from there.
+        let bumpable_crates = workspace_crates
+            .iter()
+            .filter(|p| self.baseline != Baseline::Registry || self.registry.allows(p))
+            .filter(|p| filter.matches(p))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let plan = match &self.plan {
+            Some(path) => {
+                let plan = BumpPlan::load(path)?;
+
+                // The plan is applied as is, so it should not bump crates which would not be
+                // selected without it.
+                let filtered_out = plan
+                    .crates
+                    .keys()
+                    .filter(|name| workspace_crates.iter().any(|p| p.name == **name))
+                    .filter(|name| !bumpable_crates.iter().any(|p| p.name == **name))
+                    .map(|name| format!("`{}`", name))
+                    .collect::<Vec<_>>();
+                if !filtered_out.is_empty() {
+                    bail!(
+                        "{} cannot be bumped because of `--include`, `--exclude` or `publish` of \
+                         the manifest",
+                        filtered_out.join(", ")
+                    );
+                }
+
+                let mut published = HashMap::new();
+                for name in plan.crates.keys() {
+                    published.insert(name.clone(), registry.published_version(name, true).await?);
+                }
+                plan.validate(workspace_crates, &published)
+                    .with_context(|| format!("failed to validate {}", path.display()))?;
+
+                plan
+            }
+            None => {
+                prefetch_published_versions(registry, bumpable_crates.iter().map(|p| &*p.name))
+                    .await?;
+
+                let crates_to_bump = self
+                    .get_crates_to_bump(&bumpable_crates)
+                    .context("failed to get crates to bump")?;
+
+                self.compute_plan(registry, &bumpable_crates, crates_to_bump)
+                    .await?
+            }
+        };
+
+        self.message_format.emit(&Event::PlanComputed {
+            crates: plan
+                .crates
+                .iter()
+                .map(|(name, bump)| PlannedVersion {
+                    package: name,
+                    old_version: workspace_crates
+                        .iter()
+                        .find(|p| p.name == *name)
+                        .map(|p| p.version.to_string())
+                        .unwrap_or_default(),
+                    new_version: bump.version.to_string(),
+                    breaking: bump.breaking,
+                })
+                .collect(),
+        });
+
+        if let Some(path) = &self.save_plan {
+            plan.save(path)?;
+            eprintln!("Saved plan to {}", path.display());
+            return Ok(());
+        }
+
+        let ws_root = &ws.root;
+        let mut backup = ManifestBackup::default();
+
+        let result = async {
+            bump_crates(
+                ws_root,
+                workspace_crates,
+                &plan,
+                &mut backup,
+                self.message_format,
+            )
+            .await?;
+            self.check_requirements(ws_root, &mut backup).await?;
+
+            let lock_path = ws_root.join("Cargo.lock");
+            backup.save(&lock_path)?;
+
+            update_lockfile(ws_root, self.registry.offline)
+                .await
+                .context("failed to update `Cargo.lock`")
+        }
+        .await;
+
+        match result {
+            Ok(changes) => {
+                for change in changes {
+                    eprintln!("Cargo.lock: {}", change);
+                }
+            }
+            Err(err) => {
+                eprintln!("Restoring {} file(s)", backup.files.len());
+                backup
+                    .restore()
+                    .context("failed to restore manifests after a failed bump")?;
+
+                self.message_format.emit(&Event::RolledBack {
+                    files: backup
+                        .files
+                        .iter()
+                        .map(|(path, _)| path.display().to_string())
+                        .collect(),
+                    reason: format!("{:#}", err),
+                });
+
+                return Err(err);
+            }
+        }
+
+        if self.git {
+            git_commit().await.context("failed to commit using git")?;
+        }
 
-        let publishable_crates = workspace_crates
+        Ok(())
+    }
+
+    /// Checks requirements on workspace members after patching manifests, and
+    /// fixes them if `--fix-requirements` is given.
+    ///
+    /// The workspace is loaded again, as manifests are modified by the bump.
+    async fn check_requirements(&self, ws_root: &Path, backup: &mut ManifestBackup) -> Result<()> {
+        let workspace_crates = Workspace::load().await?.members;
+        let mismatches = find_mismatched_requirements(&workspace_crates);
+
+        if mismatches.is_empty() {
+            return Ok(());
+        }
+
+        if !self.fix_requirements {
+            bail!(
+                "requirements on workspace members do not match the local versions:\n{}\nUse \
+                 `--fix-requirements` to update them",
+                mismatches
+                    .iter()
+                    .map(|m| m.to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            );
+        }
+
+        for p in &workspace_crates {
+            if mismatches.iter().any(|m| m.package == p.name) {
+                backup.save(p.manifest_path.as_ref())?;
+            }
+        }
+        backup.save(&ws_root.join("Cargo.toml"))?;
+
+        fix_requirements(ws_root, &workspace_crates, &mismatches, self.message_format).await
+    }
+
+    /// Determines new versions of `crates_to_bump` and their dependants.
+    ///
+    /// If a crate is reached from multiple crates, the most severe bump wins.
+    async fn compute_plan(
+        &self,
+        registry: &dyn Registry,
+        bumpable_crates: &[Package],
+        crates_to_bump: Vec<String>,
+    ) -> Result<BumpPlan> {
+        // Crates which can't be published don't have a meaningful published version.
+        let candidates = bumpable_crates
             .iter()
-            .filter(|p| p.publish.is_none())
+            .filter(|p| self.baseline != Baseline::Registry || can_publish(p))
             .cloned()
             .collect::<Vec<_>>();
 
-        let crates_to_bump = self
-            .get_crates_to_bump(&publishable_crates)
-            .context("failed to get crates to bump")?;
+        let mut plan = BumpPlan::default();
 
         for crate_to_bump in crates_to_bump {
             // Get list of crates to bump
             let mut dependants = Default::default();
             public_dependants(
+                registry,
                 self.interactive,
                 &mut dependants,
-                &publishable_crates,
+                &candidates,
                 &crate_to_bump,
                 !self.interactive && self.breaking,
                 !self.interactive && self.with_dependants,
             )
             .await?;
 
-            let dependants = Arc::new(dependants);
-
-            for dep in dependants.keys() {
-                match workspace_crates.iter().find(|p| p.name == **dep) {
-                    None => bail!("Package {} is not a member of workspace", crate_to_bump),
-                    Some(v) => {
-                        patch(v.clone(), dependants.clone())
-                            .await
-                            .with_context(|| format!("failed to patch {}", v.name))?;
-                    }
-                };
-            }
+            plan.merge(dependants);
         }
 
-        generate_lockfile()
-            .await
-            .context("failed to update `Cargo.lock`")?;
-
-        if self.git {
-            git_commit().await.context("failed to commit using git")?;
+        if self.local_version != LocalVersion::Ignore {
+            apply_local_versions(registry, &mut plan, bumpable_crates, self.local_version).await?;
         }
 
-        Ok(())
+        Ok(plan)
     }
 }
 
-async fn patch(package: Package, deps_to_bump: Arc<HashMap<String, Version>>) -> Result<()> {
-    eprintln!(
-        "Package({}) -> {}",
-        package.name, deps_to_bump[&package.name]
-    );
+/// Adjusts versions in `plan` based on the local versions of `packages`, and
+/// reports the decision for each crate.
+pub async fn apply_local_versions(
+    registry: &dyn Registry,
+    plan: &mut BumpPlan,
+    packages: &[Package],
+    mode: LocalVersion,
+) -> Result<()> {
+    for (name, bump) in plan.crates.iter_mut() {
+        let local = match packages.iter().find(|p| p.name == *name) {
+            Some(p) => &p.version,
+            None => continue,
+        };
+        let published = registry.published_version(name, true).await?;
+
+        let decision = match mode {
+            LocalVersion::Ignore => continue,
+            LocalVersion::Skip if *local >= bump.version => {
+                bump.version = local.clone();
+                "already bumped locally, keeping it"
+            }
+            LocalVersion::Max if *local > published => {
+                bump.version = calc_bumped_version(local.clone(), bump.breaking)?;
+                "bumped from the local version"
+            }
+            _ => "bumped from the published version",
+        };
 
-    spawn_blocking(move || -> Result<_> {
-        let toml = read_to_string(&package.manifest_path).context("failed to read error")?;
+        eprintln!(
+            "{}: published {}, local {} -> {} ({})",
+            name, published, local, bump.version, decision
+        );
+    }
 
-        let mut doc = toml
-            .parse::<toml_edit::Document>()
-            .context("toml file is invalid")?;
+    Ok(())
+}
 
-        {
-            // Bump version of package itself
-            let v = deps_to_bump[&package.name].to_string();
-            doc["package"]["version"] = toml_edit::value(&*v);
+/// Patches manifests of all crates in `plan`.
+///
+/// Every manifest is saved to `backup` before it's modified.
+async fn bump_crates(
+    ws_root: &Path,
+    workspace_crates: &[Package],
+    plan: &BumpPlan,
+    backup: &mut ManifestBackup,
+    message_format: MessageFormat,
+) -> Result<()> {
+    let versions = Arc::new(plan.versions());
+
+    for name in plan.crates.keys() {
+        match workspace_crates.iter().find(|p| p.name == *name) {
+            None => bail!("Package {} is not a member of workspace", name),
+            Some(v) => {
+                backup.save(v.manifest_path.as_ref())?;
+
+                patch(
+                    v.clone(),
+                    Some(versions[&v.name].clone()),
+                    versions.clone(),
+                    message_format,
+                )
+                .await
+                .with_context(|| format!("failed to patch {}", v.name))?;
+            }
+        };
+    }
+
+    backup.save(&ws_root.join("Cargo.toml"))?;
+    patch_workspace_deps(ws_root, versions)
+        .await
+        .context("failed to patch `[workspace.dependencies]`")?;
+
+    Ok(())
+}
+
+/// Original content of files modified by `bump`.
+///
+/// `None` means the file did not exist.
+#[derive(Debug, Default)]
+struct ManifestBackup {
+    files: Vec<(PathBuf, Option<String>)>,
+}
+
+impl ManifestBackup {
+    fn save(&mut self, path: &Path) -> Result<()> {
+        if self.files.iter().any(|(p, _)| p == path) {
+            return Ok(());
         }
 
-        // Bump version of dependencies
-        for &dep_type in &["dependencies", "dev-dependencies", "build-dependencies"] {
-            let deps_section = &mut doc[dep_type];
-            if !deps_section.is_none() {
-                //
-                let table = deps_section.as_table_mut();
-                if let Some(table) = table {
-                    for (dep_to_bump, new_version) in deps_to_bump.iter() {
-                        if table.contains_key(dep_to_bump) {
-                            let prev: &mut toml_edit::Item = &mut table[dep_to_bump];
-
-                            let new_version = toml_edit::value(new_version.to_string());
-                            // We should handle object like
-                            //
-                            // { version = "0.1", path = "./macros" }
-
-                            match prev {
-                                Item::None => {
-                                    unreachable!("{}.{} cannot be none", dep_type, dep_to_bump,)
-                                }
-                                Item::Value(v) => match v {
-                                    Value::String(_) => {
-                                        *v = new_version.as_value().unwrap().clone()
-                                    }
-                                    Value::InlineTable(v) => {
-                                        *v.get_mut("version").expect("should have version") =
-                                            new_version.as_value().unwrap().clone();
-                                    }
-                                    _ => unreachable!(
-                                        "{}.{}: cannot be unknown type {:?}",
-                                        dep_type, dep_to_bump, prev
-                                    ),
-                                },
-                                Item::Table(_) => {}
-                                Item::ArrayOfTables(_) => unreachable!(
-                                    "{}.{} cannot be array of table",
-                                    dep_type, dep_to_bump
-                                ),
-                            }
-                        }
-                    }
-                }
+        let content = if path.exists() {
+            Some(
+                read_to_string(path)
+                    .with_context(|| format!("failed to read {}", path.display()))?,
+            )
+        } else {
+            None
+        };
+        self.files.push((path.to_path_buf(), content));
+
+        Ok(())
+    }
+
+    /// Writes back all saved manifests.
+    fn restore(&self) -> Result<()> {
+        for (path, content) in &self.files {
+            match content {
+                Some(content) => write(path, content),
+                None if path.exists() => remove_file(path),
+                None => Ok(()),
             }
+            .with_context(|| format!("failed to restore {}", path.display()))?;
         }
 
-        write(&package.manifest_path, doc.to_string())
-            .context("failed to save modified Cargo.toml")?;
-
         Ok(())
-    })
-    .await
-    .expect("failed to edit toml file")
+    }
 }
 
 /// Returns `(breaking, dependants)`.
@@ -221,6 +527,8 @@ fn determine_dependants_to_bump(
         return Ok((true, dependants.iter().map(|p| p.name.clone()).collect()));
     }
 
+    ensure_terminal(INTERACTIVE_HINT)?;
+
     {
         let q = Question::confirm("breaking")
             .message(format!("Is the change of `{}` breaking change?", cur_crate))
@@ -259,11 +567,31 @@ fn determine_dependants_to_bump(
     }
 }
 
-/// This is recursive and returned value does not contain original crate itself.
+const INTERACTIVE_HINT: &str = "`--interactive` requires a terminal. Use `--breaking` or \
+                                `--with-dependants` instead, or apply a saved plan using `--plan \
+                                <FILE>`.";
+
+/// Fails with `hint` if stdin is not a terminal, as prompts would hang or fail
+/// with a cryptic error.
+fn ensure_terminal(hint: &str) -> Result<()> {
+    if !io::stdin().is_terminal() {
+        bail!("cannot prompt because stdin is not a terminal.\n{}", hint);
+    }
+
+    Ok(())
+}
+
+/// Adds `crate_to_bump` to `dependants` with a version bumped from the one
+/// published to `registry`, along with its dependants if `breaking` or
+/// `with_dependants` is true.
+///
+/// Dependants are selected using prompts if `interactive` is true. `packages`
+/// should contain only crates which can be bumped.
 #[async_recursion]
-async fn public_dependants<'a>(
+pub async fn public_dependants<'a>(
+    registry: &'a dyn Registry,
     interactive: bool,
-    dependants: &'a mut HashMap<String, Version>,
+    dependants: &'a mut BumpPlan,
     packages: &'a [Package],
     crate_to_bump: &'a str,
     breaking: bool,
@@ -275,7 +603,7 @@ async fn public_dependants<'a>(
     //     packages.iter().map(|v| &*v.name).collect::<Vec<_>>()
     // );
 
-    if dependants.contains_key(&crate_to_bump.to_string()) {
+    if dependants.crates.contains_key(crate_to_bump) {
         return Ok(());
     }
 
@@ -285,45 +613,52 @@ async fn public_dependants<'a>(
     } else {
         (breaking, vec![])
     };
+    let mut selected = dependants_to_bump.clone();
 
     for p in packages {
-        if !can_publish(p) {
-            continue;
-        }
-
-        if dependants.contains_key(&p.name) {
+        if dependants.crates.contains_key(&p.name) {
             continue;
         }
 
         if p.name == crate_to_bump {
-            let previous = fetch_published_version(&p.name, true).await?;
+            let previous = registry.published_version(&p.name, true).await?;
             let new_version = calc_bumped_version(previous, breaking)?;
 
-            dependants.insert(p.name.clone(), new_version);
+            dependants.crates.insert(
+                p.name.clone(),
+                PlannedBump {
+                    version: new_version,
+                    breaking,
+                    dependants: vec![],
+                },
+            );
             continue;
         }
 
         if !interactive && (breaking || with_dependants) {
-            for dep in &p.dependencies {
-                if dep.name == crate_to_bump {
-                    eprintln!("{} depends on {}", p.name, dep.name);
-
-                    public_dependants(
-                        interactive,
-                        dependants,
-                        packages,
-                        &p.name,
-                        breaking,
-                        with_dependants,
-                    )
-                    .await?;
-                }
+            // A crate may depend on `crate_to_bump` multiple times, e.g. as a
+            // dependency and a dev-dependency.
+            if p.dependencies.iter().any(|dep| dep.name == crate_to_bump) {
+                eprintln!("{} depends on {}", p.name, crate_to_bump);
+                selected.push(p.name.clone());
+
+                public_dependants(
+                    registry,
+                    interactive,
+                    dependants,
+                    packages,
+                    &p.name,
+                    breaking,
+                    with_dependants,
+                )
+                .await?;
             }
         }
     }
 
     for dep in dependants_to_bump {
         public_dependants(
+            registry,
             interactive,
             dependants,
             packages,
@@ -334,10 +669,15 @@ async fn public_dependants<'a>(
         .await?;
     }
 
+    if let Some(planned) = dependants.crates.get_mut(crate_to_bump) {
+        planned.dependants = selected;
+    }
+
     Ok(())
 }
 
-fn calc_bumped_version(mut v: Version, breaking: bool) -> Result<Version> {
+/// Returns the next version of `v`, treating `0.x` versions like cargo does.
+pub fn calc_bumped_version(mut v: Version, breaking: bool) -> Result<Version> {
     // Semver treats 0.x specially
     if v.major == 0 {
         if breaking {
@@ -354,18 +694,6 @@ fn calc_bumped_version(mut v: Version, breaking: bool) -> Result<Version> {
     Ok(v)
 }
 
-async fn generate_lockfile() -> Result<()> {
-    Command::new("cargo")
-        .arg("metadata")
-        .arg("--format-version")
-        .arg("1")
-        .output()
-        .await
-        .context("failed to generate lockfile")?;
-
-    Ok(())
-}
-
 async fn git_commit() -> Result<()> {
     let mut files = vec![];
     for e in WalkDir::new(".") {
@@ -380,18 +708,36 @@ async fn git_commit() -> Result<()> {
         }
     }
 
-    let mut cmd = Command::new("git");
-    cmd.arg("commit");
-
-    for file in &*files {
+    let mut paths = vec![];
+    for file in files {
         if !is_ignored_by_git(&file).await? {
-            cmd.arg(file);
+            paths.push(file);
         }
     }
 
-    cmd.arg("-m").arg("Bump version");
+    // `Cargo.lock` may be created by the bump, and `git commit <paths>` fails
+    // for untracked files.
+    run_git(Command::new("git").arg("add").arg("--").args(&paths))
+        .await
+        .context("failed to add files to the index")?;
+    run_git(
+        Command::new("git")
+            .arg("commit")
+            .arg("-m")
+            .arg("Bump version")
+            .arg("--")
+            .args(&paths),
+    )
+    .await
+}
 
-    cmd.status().await.context("failed to run git")?;
+/// Runs git, printing its stdout to stderr as stdout is reserved for events.
+async fn run_git(cmd: &mut Command) -> Result<()> {
+    let output = cmd.output().await.context("failed to run git")?;
+    eprint!("{}", String::from_utf8_lossy(&output.stdout));
+    if !output.status.success() {
+        bail!("git failed:\n{}", String::from_utf8_lossy(&output.stderr));
+    }
 
     Ok(())
 }
@@ -1,25 +1,93 @@
-use anyhow::{Context, Result};
+//! Loading the workspace using `cargo metadata`.
+
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
 use cargo_metadata::Package;
+use glob::Pattern;
 use tokio::task::spawn_blocking;
 
-pub async fn fetch_ws_crates() -> Result<Vec<Package>> {
-    spawn_blocking(|| -> Result<_> {
-        let res = cargo_metadata::MetadataCommand::new()
-            .no_deps()
-            .exec()
-            .context("failed to run `cargo metadata`")?;
-        let packages = res.packages;
-        let members = res.workspace_members;
-
-        let mut ws_packages = packages
-            .into_iter()
-            .filter(|p| members.iter().any(|pid| *pid == p.id))
+use crate::config::Config;
+
+/// Members and settings of the current workspace, loaded using a single
+/// `cargo metadata` call.
+///
+/// Commands load it once and pass it down, so that every step sees the same
+/// manifests. It should be loaded again only after modifying manifests.
+#[derive(Debug, Clone)]
+pub struct Workspace {
+    /// Root directory of the workspace.
+    pub root: PathBuf,
+    /// Target directory of the workspace, where `cargo package` stores
+    /// `.crate` files.
+    pub target_dir: PathBuf,
+    /// Members of the workspace, sorted by name.
+    pub members: Vec<Package>,
+    /// `[workspace.metadata.mono]` of the root `Cargo.toml`.
+    pub config: Config,
+}
+
+impl Workspace {
+    /// Runs `cargo metadata` for the workspace of the current directory.
+    pub async fn load() -> Result<Self> {
+        spawn_blocking(|| -> Result<_> {
+            let res = cargo_metadata::MetadataCommand::new()
+                .no_deps()
+                .exec()
+                .context("failed to run `cargo metadata`")?;
+
+            let config = match res.workspace_metadata.get("mono") {
+                Some(v) => serde_json::from_value(v.clone())
+                    .context("`[workspace.metadata.mono]` is invalid")?,
+                None => Default::default(),
+            };
+
+            let members = res.workspace_members;
+            let mut packages = res
+                .packages
+                .into_iter()
+                .filter(|p| members.iter().any(|pid| *pid == p.id))
+                .collect::<Vec<_>>();
+            packages.sort_by(|a, b| a.name.cmp(&b.name));
+
+            Ok(Workspace {
+                root: res.workspace_root.into_std_path_buf(),
+                target_dir: res.target_directory.into_std_path_buf(),
+                members: packages,
+                config,
+            })
+        })
+        .await
+        .expect("failed to fetch metadata")
+    }
+}
+
+/// Resolves crate names and glob patterns like `swc_ecma_*` to the names of
+/// matching packages.
+///
+/// Each pattern should match at least one package.
+pub fn match_crates(packages: &[Package], patterns: &[String]) -> Result<Vec<String>> {
+    let mut names = vec![];
+
+    for pattern in patterns {
+        let matcher =
+            Pattern::new(pattern).with_context(|| format!("invalid pattern `{}`", pattern))?;
+
+        let matched = packages
+            .iter()
+            .filter(|p| matcher.matches(&p.name))
             .collect::<Vec<_>>();
 
-        ws_packages.sort_by(|a, b| a.name.cmp(&b.name));
+        if matched.is_empty() {
+            bail!("no crate in the workspace matches `{}`", pattern);
+        }
+
+        for p in matched {
+            if !names.contains(&p.name) {
+                names.push(p.name.clone());
+            }
+        }
+    }
 
-        Ok(ws_packages)
-    })
-    .await
-    .expect("failed to fetch metadata")
+    Ok(names)
 }
@@ -0,0 +1,88 @@
+//! Checks run before publishing, to find problems of all crates at once.
+
+use anyhow::{bail, Context, Result};
+use cargo_metadata::Package;
+use tokio::process::Command;
+
+use crate::{
+    crates_io::{has_no_version, is_stripped_on_publish},
+    requirements::find_mismatched_requirements,
+};
+
+/// Checks if `packages` can be published, and reports all problems at once.
+///
+/// `ws_packages` should contain all workspace members.
+pub async fn check_packages(ws_packages: &[Package], packages: &[&Package]) -> Result<()> {
+    let mut problems = vec![];
+
+    for p in packages {
+        eprintln!("Checking `{}`", p.name);
+
+        for problem in check_package(p).await? {
+            problems.push(format!("{}: {}", p.name, problem));
+        }
+    }
+
+    for m in find_mismatched_requirements(ws_packages) {
+        if packages.iter().any(|p| p.name == m.package) {
+            problems.push(m.to_string());
+        }
+    }
+
+    if !problems.is_empty() {
+        bail!(
+            "found {} problem(s) before publishing:\n{}",
+            problems.len(),
+            problems.join("\n")
+        );
+    }
+
+    Ok(())
+}
+
+async fn check_package(p: &Package) -> Result<Vec<String>> {
+    let mut problems = vec![];
+
+    if p.license.is_none() && p.license_file.is_none() {
+        problems.push("`license` or `license-file` is missing".to_string());
+    }
+    if p.description.is_none() {
+        problems.push("`description` is missing".to_string());
+    }
+    if p.repository.is_none() {
+        problems.push("`repository` is missing".to_string());
+    }
+
+    for dep in &p.dependencies {
+        if is_stripped_on_publish(dep) {
+            continue;
+        }
+
+        if has_no_version(dep) {
+            problems.push(format!(
+                "dependency `{}` does not specify a version",
+                dep.name
+            ));
+            continue;
+        }
+    }
+
+    let output = Command::new("cargo")
+        .arg("package")
+        .arg("--list")
+        .arg("--allow-dirty")
+        .arg("--manifest-path")
+        .arg(&p.manifest_path)
+        .output()
+        .await
+        .context("failed to run `cargo package --list`")?;
+
+    if !output.status.success() {
+        problems.push(format!(
+            "`cargo package --list` failed:\n{}",
+            String::from_utf8_lossy(&output.stderr).trim_end()
+        ));
+    }
+
+    Ok(problems)
+}
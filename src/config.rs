@@ -0,0 +1,15 @@
+//! Configuration of `cargo mono` stored in the workspace.
+
+use serde::Deserialize;
+
+/// Configuration in `[workspace.metadata.mono]` of the root `Cargo.toml`.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Config {
+    /// Glob patterns of crates to publish or bump. All crates are included if
+    /// empty.
+    pub include: Vec<String>,
+
+    /// Glob patterns of crates to skip.
+    pub exclude: Vec<String>,
+}
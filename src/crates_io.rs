@@ -1,87 +1,349 @@
-use anyhow::{Context, Result};
-use cargo_metadata::Package;
-use dashmap::DashMap;
-use once_cell::sync::Lazy;
-use semver::Version;
+//! Client of the sparse index protocol, used by crates.io and other
+//! registries.
+
+use std::{collections::BTreeMap, fs::read_to_string, path::Path};
+
+use anyhow::{bail, Context, Result};
+use cargo_metadata::{Dependency, DependencyKind, Package};
+use reqwest::{
+    header::{HeaderMap, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+    StatusCode,
+};
+use semver::{Version, VersionReq};
 use serde::Deserialize;
 
-/// Fetches the current version from crates.io
-pub async fn fetch_published_version(package_name: &str, allow_not_found: bool) -> Result<Version> {
-    static CACHE: Lazy<DashMap<String, Version>> = Lazy::new(DashMap::new);
+use crate::{
+    http,
+    index_cache::{self, CachedIndex},
+};
 
-    if let Some(v) = CACHE.get(package_name) {
-        return Ok(v.clone());
-    }
+/// URL of the sparse index of crates.io.
+pub const CRATES_IO_INDEX_URL: &str = "https://index.crates.io";
 
-    let body = reqwest::get(&build_url(package_name)).await?.text().await?;
-
-    let mut v = body
-        .lines()
-        .into_iter()
-        .filter_map(|line| {
-            let desc = serde_json::from_str::<Descriptor>(&line);
-            let line = match desc {
-                Ok(v) => v,
-                Err(err) => {
-                    return Some(Err(anyhow::anyhow!(
-                        "failed to parse line: {:?}\n{}",
-                        err,
-                        line
-                    )))
-                }
-            };
-
-            Some(Ok(line.vers))
+/// Fetches all published versions of a crate from the sparse index at
+/// `index_url`.
+///
+/// Returns an empty list if the crate is not published. If `offline` is true,
+/// index files are read only from the disk cache.
+pub async fn fetch_index_entries(
+    index_url: &str,
+    package_name: &str,
+    offline: bool,
+) -> Result<Vec<IndexEntry>> {
+    let body = fetch_index_file(index_url, package_name, offline).await?;
+
+    body.lines()
+        .map(|line| {
+            serde_json::from_str::<IndexEntry>(line)
+                .map_err(|err| anyhow::anyhow!("failed to parse line: {:?}\n{}", err, line))
         })
         .collect::<Result<Vec<_>>>()
-        .with_context(|| format!("failed to parse index of {}", package_name))?;
+        .with_context(|| format!("failed to parse index of {}", package_name))
+}
 
-    v.sort_by(|a, b| b.cmp(a));
+/// Returns true if `p` can be published to a registry.
+pub fn can_publish(p: &Package) -> bool {
+    unpublishable_reason(p).is_none()
+}
 
-    if allow_not_found && v.is_empty() {
-        CACHE.insert(package_name.to_string(), Version::new(0, 0, 0));
-        return Ok(Version::new(0, 0, 0));
+/// Returns the reason why [can_publish] returns false for `p`.
+pub fn unpublishable_reason(p: &Package) -> Option<String> {
+    // Skip if publish is false
+    if !is_publish_enabled(p) {
+        return Some("`publish = false`".into());
     }
 
-    CACHE.insert(package_name.to_string(), v[0].clone());
-    Ok(v[0].clone())
+    for d in &p.dependencies {
+        if is_stripped_on_publish(d) {
+            continue;
+        }
+
+        if has_no_version(d) {
+            return Some(format!(
+                "dependency `{}` does not specify a version",
+                d.name
+            ));
+        }
+    }
+
+    None
 }
 
-pub fn can_publish(p: &Package) -> bool {
-    // Skip if publish is false
-    match &p.publish {
-        Some(v) if v.is_empty() => return false,
-        _ => {}
+/// Returns true if `dep` does not specify a version, like
+/// `foo = { path = "../foo" }`.
+pub fn has_no_version(dep: &Dependency) -> bool {
+    // `*` is displayed as `>=0.0.0` by semver, so it can't be compared as a
+    // string.
+    dep.req == VersionReq::parse("*").unwrap()
+}
+
+/// Returns true if cargo removes `dep` from the published manifest, which is
+/// the case for dev-dependencies without a version.
+pub fn is_stripped_on_publish(dep: &Dependency) -> bool {
+    dep.kind == DependencyKind::Development && has_no_version(dep)
+}
+
+/// Returns false if `publish = false` is specified.
+pub fn is_publish_enabled(p: &Package) -> bool {
+    !matches!(&p.publish, Some(v) if v.is_empty())
+}
+
+/// Fetches the index file of a crate using conditional requests, based on
+/// the disk cache.
+///
+/// `file://` URLs are read from the disk directly, without caching.
+async fn fetch_index_file(index_url: &str, package_name: &str, offline: bool) -> Result<String> {
+    let path = index_path(package_name);
+    let base = index_url.trim_end_matches('/');
+    let url = format!("{}/{}", base, path);
+
+    if let Some(dir) = base.strip_prefix("file://") {
+        let file = Path::new(dir).join(&path);
+        if !file.exists() {
+            return Ok(String::new());
+        }
+
+        return read_to_string(&file).with_context(|| format!("failed to read {}", file.display()));
     }
 
-    for d in &p.dependencies {
-        if d.req.to_string() == "*" {
-            return false;
+    let cached = index_cache::load(&url);
+
+    if offline {
+        return match cached {
+            Some(cached) => Ok(cached.body),
+            None => (base == CRATES_IO_INDEX_URL)
+                .then(|| index_cache::load_from_cargo_cache(&path))
+                .flatten()
+                .with_context(|| {
+                    format!(
+                        "index of `{}` is not cached; run without `--offline`",
+                        package_name
+                    )
+                }),
+        };
+    }
+
+    let mut headers = HeaderMap::new();
+    if let Some(cached) = &cached {
+        if let Some(v) = cached.etag.as_deref().and_then(|v| v.parse().ok()) {
+            headers.insert(IF_NONE_MATCH, v);
+        }
+        if let Some(v) = cached.last_modified.as_deref().and_then(|v| v.parse().ok()) {
+            headers.insert(IF_MODIFIED_SINCE, v);
         }
     }
 
-    true
+    let res = http::client()?.get(&url, headers).await?;
+    if res.status() == StatusCode::NOT_MODIFIED {
+        if let Some(cached) = cached {
+            return Ok(cached.body);
+        }
+    }
+
+    let header = |name| {
+        res.headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string())
+    };
+    let etag = header(ETAG);
+    let last_modified = header(LAST_MODIFIED);
+    let status = res.status();
+    // crates.io responds with 404 for crates which are not published yet. The
+    // sparse index protocol treats 410 and 451 the same way.
+    let body = if matches!(
+        status,
+        StatusCode::NOT_FOUND | StatusCode::GONE | StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS
+    ) {
+        String::new()
+    } else if status.is_success() {
+        res.text()
+            .await
+            .with_context(|| format!("failed to read the response from {}", url))?
+    } else {
+        bail!("{} responded with unexpected status {}", url, status)
+    };
+
+    let stored = index_cache::store(&CachedIndex {
+        url,
+        etag,
+        last_modified,
+        body: body.clone(),
+    });
+    if let Err(err) = stored {
+        eprintln!("Failed to cache index of `{}`: {:?}", package_name, err);
+    }
+
+    Ok(body)
 }
 
-fn build_url(name: &str) -> String {
+/// Path of the index file of a crate, relative to the root of the index.
+pub fn index_path(name: &str) -> String {
     let name = name.to_ascii_lowercase();
     match name.len() {
-        1 => format!("https://index.crates.io/1/{name}"),
-        2 => format!("https://index.crates.io/2/{name}"),
+        1 => format!("1/{name}"),
+        2 => format!("2/{name}"),
         3 => {
             let first_char = name.chars().next().unwrap();
-            format!("https://index.crates.io/3/{first_char}/{name}")
+            format!("3/{first_char}/{name}")
         }
         _ => {
             let first_two = &name[0..2];
             let second_two = &name[2..4];
 
-            format!("https://index.crates.io/{first_two}/{second_two}/{name}",)
+            format!("{first_two}/{second_two}/{name}")
         }
     }
 }
 
-#[derive(Debug, Deserialize)]
-struct Descriptor {
+/// A record of the sparse index, which describes a published version of a
+/// crate.
+///
+/// See <https://doc.rust-lang.org/cargo/reference/registry-index.html#json-schema>
+#[derive(Debug, Clone, Deserialize)]
+pub struct IndexEntry {
+    /// Name of the crate.
+    pub name: String,
+    /// The published version.
     pub vers: Version,
+    /// Dependencies of the version.
+    #[serde(default)]
+    pub deps: Vec<IndexDependency>,
+    /// SHA256 checksum of the `.crate` file.
+    pub cksum: String,
+    /// Features, except ones in `features2`.
+    #[serde(default)]
+    pub features: BTreeMap<String, Vec<String>>,
+    /// Features using new syntax like `dep:foo`, stored separately by
+    /// crates.io.
+    #[serde(default)]
+    pub features2: BTreeMap<String, Vec<String>>,
+    /// True if the version is yanked.
+    #[serde(default)]
+    pub yanked: bool,
+    /// Value of `package.links`.
+    #[serde(default)]
+    pub links: Option<String>,
+    /// The minimum supported Rust version.
+    #[serde(default)]
+    pub rust_version: Option<String>,
+}
+
+impl IndexEntry {
+    /// Finds a dependency on the crate named `name`, even if it's renamed.
+    pub fn dependency(&self, name: &str) -> Option<&IndexDependency> {
+        self.deps.iter().find(|dep| dep.crate_name() == name)
+    }
+
+    /// Returns both of `features` and `features2`.
+    pub fn all_features(&self) -> BTreeMap<&str, &[String]> {
+        self.features
+            .iter()
+            .chain(&self.features2)
+            .map(|(k, v)| (&**k, &**v))
+            .collect()
+    }
+}
+
+/// A dependency in [IndexEntry].
+#[derive(Debug, Clone, Deserialize)]
+pub struct IndexDependency {
+    /// Name of the dependency. If the dependency is renamed, this is the new
+    /// name and the original name is stored in `package`.
+    pub name: String,
+    /// The version requirement, as written in the manifest.
+    pub req: String,
+    /// Features enabled for the dependency.
+    #[serde(default)]
+    pub features: Vec<String>,
+    /// True if the dependency is optional.
+    #[serde(default)]
+    pub optional: bool,
+    /// True if default features are enabled.
+    #[serde(default = "default_true")]
+    pub default_features: bool,
+    /// Target platform like `cfg(unix)`, if the dependency is target-specific.
+    #[serde(default)]
+    pub target: Option<String>,
+    /// `normal`, `dev` or `build`.
+    #[serde(default)]
+    pub kind: Option<String>,
+    /// URL of the index of the registry, or `None` for the same registry.
+    #[serde(default)]
+    pub registry: Option<String>,
+    /// Original name of a renamed dependency.
+    #[serde(default)]
+    pub package: Option<String>,
+}
+
+impl IndexDependency {
+    /// Name of the depended crate, regardless of renaming.
+    pub fn crate_name(&self) -> &str {
+        self.package.as_deref().unwrap_or(&self.name)
+    }
+
+    /// Parses `req`.
+    pub fn version_req(&self) -> Result<VersionReq> {
+        VersionReq::parse(&self.req)
+            .with_context(|| format!("invalid requirement `{}` on `{}`", self.req, self.name))
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use semver::{Version, VersionReq};
+
+    use super::IndexEntry;
+
+    /// `xtensa-lx-rt` 0.23.0, as served by the sparse index of crates.io.
+    const RECORD: &str = r#"{"name":"xtensa-lx-rt","vers":"0.23.0","deps":[{"name":"defmt","req":"^1.1","features":[],"optional":true,"default_features":true,"target":null,"kind":"normal"},{"name":"document-features","req":"^0.2","features":[],"optional":false,"default_features":true,"target":null,"kind":"normal"},{"name":"macros","req":"^0.5.0","features":[],"optional":false,"default_features":true,"target":null,"kind":"normal","package":"xtensa-lx-rt-proc-macros"},{"name":"xtensa-lx","req":"^0.13.0","features":[],"optional":false,"default_features":true,"target":null,"kind":"normal"}],"cksum":"07933551193a68b5b0a94a7c428e9626adb559661e0f4b048ac5ec1fa4bed334","features":{"float-save-restore":[]},"features2":{"defmt":["dep:defmt"]},"yanked":false,"links":"xtensa-lx-rt","rust_version":"1.95.0","pubtime":"2026-08-24T16:58:03Z","v":2}"#;
+
+    #[test]
+    fn parse_index_record() {
+        let entry: IndexEntry = serde_json::from_str(RECORD).unwrap();
+
+        assert_eq!(entry.name, "xtensa-lx-rt");
+        assert_eq!(entry.vers, Version::parse("0.23.0").unwrap());
+        assert_eq!(entry.deps.len(), 4);
+        assert_eq!(entry.links.as_deref(), Some("xtensa-lx-rt"));
+        assert_eq!(entry.rust_version.as_deref(), Some("1.95.0"));
+        assert!(!entry.yanked);
+    }
+
+    #[test]
+    fn renamed_dependency() {
+        let entry: IndexEntry = serde_json::from_str(RECORD).unwrap();
+
+        let dep = entry.dependency("xtensa-lx-rt-proc-macros").unwrap();
+        assert_eq!(dep.name, "macros");
+        assert_eq!(dep.crate_name(), "xtensa-lx-rt-proc-macros");
+        assert_eq!(
+            dep.version_req().unwrap(),
+            VersionReq::parse("^0.5.0").unwrap()
+        );
+        // Only the original name is matched.
+        assert!(entry.dependency("macros").is_none());
+
+        let dep = entry.dependency("defmt").unwrap();
+        assert!(dep.optional);
+        assert_eq!(dep.kind.as_deref(), Some("normal"));
+        assert_eq!(dep.target, None);
+    }
+
+    #[test]
+    fn features_and_features2() {
+        let entry: IndexEntry = serde_json::from_str(RECORD).unwrap();
+
+        let features = entry.all_features();
+        assert_eq!(
+            features.keys().copied().collect::<Vec<_>>(),
+            ["defmt", "float-save-restore"]
+        );
+        assert_eq!(features["defmt"], ["dep:defmt".to_string()]);
+        assert!(features["float-save-restore"].is_empty());
+    }
 }
@@ -0,0 +1,58 @@
+//! Filters for crates managed by `cargo mono`.
+
+use anyhow::{Context, Result};
+use cargo_metadata::Package;
+use clap::Args;
+use glob::Pattern;
+
+use crate::config::Config;
+
+/// Glob patterns to select crates, used by `bump` and `publish`.
+///
+/// Patterns given on the command line take precedence over the ones in
+/// `[workspace.metadata.mono]`.
+#[derive(Debug, Args)]
+pub struct FilterArgs {
+    /// Only consider crates matching the glob pattern. Can be repeated.
+    #[clap(long, value_name = "PATTERN")]
+    pub include: Vec<String>,
+
+    /// Ignore crates matching the glob pattern. Can be repeated.
+    #[clap(long, value_name = "PATTERN")]
+    pub exclude: Vec<String>,
+}
+
+impl FilterArgs {
+    /// Creates a filter, using `config` for options not given on the command
+    /// line.
+    pub fn build(&self, config: &Config) -> Result<CrateFilter> {
+        let pick = |cli: &[String], config: &[String]| {
+            let patterns = if cli.is_empty() { config } else { cli };
+
+            patterns
+                .iter()
+                .map(|p| Pattern::new(p).with_context(|| format!("invalid pattern `{}`", p)))
+                .collect::<Result<Vec<_>>>()
+        };
+
+        Ok(CrateFilter {
+            include: pick(&self.include, &config.include)?,
+            exclude: pick(&self.exclude, &config.exclude)?,
+        })
+    }
+}
+
+/// Patterns selecting crates, built from [FilterArgs].
+#[derive(Debug, Default)]
+pub struct CrateFilter {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+impl CrateFilter {
+    /// Returns true if `p` is included and not excluded.
+    pub fn matches(&self, p: &Package) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|pat| pat.matches(&p.name)))
+            && !self.exclude.iter().any(|pat| pat.matches(&p.name))
+    }
+}
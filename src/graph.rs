@@ -0,0 +1,287 @@
+//! `cargo mono graph`, which prints the dependency graph of workspace members.
+
+use std::{collections::HashSet, fmt::Write};
+
+use anyhow::{bail, Result};
+use cargo_metadata::{DependencyKind, Package};
+use clap::{Args, ValueEnum};
+use petgraph::{
+    graphmap::DiGraphMap,
+    visit::{Dfs, Reversed, Walker},
+};
+use serde::Serialize;
+
+use crate::{cargo_workspace::Workspace, crates_io::unpublishable_reason};
+
+/// Prints the dependency graph of workspace members.
+///
+/// Edges point from a dependant to its dependency.
+#[derive(Debug, Args)]
+pub struct GraphCommand {
+    /// Output format.
+    #[clap(long, value_enum, default_value_t)]
+    pub format: GraphFormat,
+
+    /// Include dev-dependencies.
+    #[clap(long)]
+    pub dev: bool,
+
+    /// Include build-dependencies.
+    #[clap(long)]
+    pub build: bool,
+
+    /// Highlight the crate and crates depending on it, directly or
+    /// transitively.
+    #[clap(long, value_name = "CRATE")]
+    pub dependants_of: Option<String>,
+
+    /// Highlight the crate and crates it depends on, directly or transitively.
+    #[clap(long, value_name = "CRATE")]
+    pub dependencies_of: Option<String>,
+
+    /// Show versions on nodes.
+    #[clap(long)]
+    pub versions: bool,
+
+    /// Show crates which can't be published with dashed borders.
+    #[clap(long)]
+    pub publishability: bool,
+}
+
+/// Output format of `cargo mono graph`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum GraphFormat {
+    /// Graphviz.
+    #[default]
+    Dot,
+    /// Mermaid flowchart, which can be embedded in Markdown.
+    Mermaid,
+    /// JSON with versions, publishability and edges of all kinds. `--dev`
+    /// and `--build` only affect highlighting.
+    Json,
+}
+
+/// A workspace member in [WorkspaceGraph].
+#[derive(Debug, Serialize)]
+pub struct Node {
+    /// Name of the crate.
+    pub name: String,
+    /// The local version.
+    pub version: String,
+    /// True if the crate can be published.
+    pub publishable: bool,
+    /// Why the crate can't be published.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// True if the crate is selected by `--dependants-of` or
+    /// `--dependencies-of`.
+    pub highlighted: bool,
+}
+
+/// A dependency between workspace members in [WorkspaceGraph].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct Edge {
+    /// Name of the dependant.
+    pub from: String,
+    /// Name of the dependency.
+    pub to: String,
+    /// `normal`, `dev` or `build`.
+    pub kind: &'static str,
+}
+
+/// Dependency graph of workspace members.
+#[derive(Debug, Serialize)]
+pub struct WorkspaceGraph {
+    /// Workspace members, sorted by name.
+    pub nodes: Vec<Node>,
+    /// Dependencies, sorted by the dependant and the dependency.
+    pub edges: Vec<Edge>,
+}
+
+impl GraphCommand {
+    /// Runs the command in the current workspace.
+    pub async fn run(&self) -> Result<()> {
+        let ws = Workspace::load().await?;
+        let graph = self.build(&ws.members)?;
+
+        let output = match self.format {
+            GraphFormat::Dot => self.to_dot(&graph),
+            GraphFormat::Mermaid => self.to_mermaid(&graph),
+            GraphFormat::Json => serde_json::to_string_pretty(&graph)? + "\n",
+        };
+        print!("{}", output);
+
+        Ok(())
+    }
+
+    /// Builds the graph of `packages`, which should be workspace members.
+    pub fn build(&self, packages: &[Package]) -> Result<WorkspaceGraph> {
+        let mut graph = DiGraphMap::<usize, ()>::new();
+        let mut edges = vec![];
+        for (from, p) in packages.iter().enumerate() {
+            graph.add_node(from);
+
+            for dep in &p.dependencies {
+                let (kind, selected) = match dep.kind {
+                    DependencyKind::Development => ("dev", self.dev),
+                    DependencyKind::Build => ("build", self.build),
+                    _ => ("normal", true),
+                };
+                if !selected && self.format != GraphFormat::Json {
+                    continue;
+                }
+
+                if let Some(to) = packages.iter().position(|p| p.name == dep.name) {
+                    let edge = Edge {
+                        from: p.name.clone(),
+                        to: dep.name.clone(),
+                        kind,
+                    };
+                    // Target-specific dependencies may be declared multiple times.
+                    if !edges.contains(&edge) {
+                        edges.push(edge);
+                    }
+                    if selected {
+                        graph.add_edge(from, to, ());
+                    }
+                }
+            }
+        }
+
+        edges.sort_by(|a, b| (&a.from, &a.to, a.kind).cmp(&(&b.from, &b.to, b.kind)));
+
+        let find = |name: &str| match packages.iter().position(|p| p.name == name) {
+            Some(v) => Ok(v),
+            None => bail!("`{}` is not a member of the workspace", name),
+        };
+
+        let mut highlighted = HashSet::new();
+        if let Some(name) = &self.dependencies_of {
+            highlighted.extend(Dfs::new(&graph, find(name)?).iter(&graph));
+        }
+        if let Some(name) = &self.dependants_of {
+            let reversed = Reversed(&graph);
+            highlighted.extend(Dfs::new(reversed, find(name)?).iter(reversed));
+        }
+
+        Ok(WorkspaceGraph {
+            nodes: packages
+                .iter()
+                .enumerate()
+                .map(|(i, p)| {
+                    let reason = unpublishable_reason(p);
+
+                    Node {
+                        name: p.name.clone(),
+                        version: p.version.to_string(),
+                        publishable: reason.is_none(),
+                        reason,
+                        highlighted: highlighted.contains(&i),
+                    }
+                })
+                .collect(),
+            edges,
+        })
+    }
+
+    fn label(&self, node: &Node) -> String {
+        if self.versions {
+            format!("{} {}", node.name, node.version)
+        } else {
+            node.name.clone()
+        }
+    }
+
+    /// Formats the graph for Graphviz.
+    pub fn to_dot(&self, graph: &WorkspaceGraph) -> String {
+        let mut out = String::from("digraph {\n");
+
+        for node in &graph.nodes {
+            let mut attrs = vec![format!("label={:?}", self.label(node))];
+            let mut styles = vec![];
+            if self.publishability && !node.publishable {
+                styles.push("dashed");
+            }
+            if node.highlighted {
+                styles.push("filled");
+                attrs.push("fillcolor=yellow".into());
+            }
+            if !styles.is_empty() {
+                attrs.push(format!("style={:?}", styles.join(",")));
+            }
+
+            writeln!(out, "    {:?} [{}];", node.name, attrs.join(", ")).unwrap();
+        }
+
+        for edge in &graph.edges {
+            match edge.kind {
+                "normal" => writeln!(out, "    {:?} -> {:?};", edge.from, edge.to),
+                kind => writeln!(
+                    out,
+                    "    {:?} -> {:?} [style=dashed, label={:?}];",
+                    edge.from, edge.to, kind
+                ),
+            }
+            .unwrap();
+        }
+
+        out.push_str("}\n");
+        out
+    }
+
+    /// Formats the graph as a Mermaid flowchart.
+    pub fn to_mermaid(&self, graph: &WorkspaceGraph) -> String {
+        let mut out = String::from("flowchart TD\n");
+
+        // Crate names may contain characters which are not allowed in ids.
+        for (i, node) in graph.nodes.iter().enumerate() {
+            writeln!(out, "    n{}[\"{}\"]", i, self.label(node)).unwrap();
+        }
+
+        let id = |name: &str| {
+            graph
+                .nodes
+                .iter()
+                .position(|node| node.name == name)
+                .unwrap()
+        };
+        for edge in &graph.edges {
+            let (from, to) = (id(&edge.from), id(&edge.to));
+
+            match edge.kind {
+                "normal" => writeln!(out, "    n{} --> n{}", from, to),
+                kind => writeln!(out, "    n{} -.->|{}| n{}", from, kind, to),
+            }
+            .unwrap();
+        }
+
+        let class = |out: &mut String, name: &str, style: &str, f: &dyn Fn(&Node) -> bool| {
+            let ids = graph
+                .nodes
+                .iter()
+                .enumerate()
+                .filter(|(_, node)| f(node))
+                .map(|(i, _)| format!("n{}", i))
+                .collect::<Vec<_>>();
+
+            if !ids.is_empty() {
+                writeln!(out, "    classDef {} {}", name, style).unwrap();
+                writeln!(out, "    class {} {}", ids.join(","), name).unwrap();
+            }
+        };
+
+        if self.publishability {
+            class(
+                &mut out,
+                "unpublishable",
+                "stroke-dasharray: 5 5",
+                &|node| !node.publishable,
+            );
+        }
+        class(&mut out, "highlighted", "fill:#ff0", &|node| {
+            node.highlighted
+        });
+
+        out
+    }
+}
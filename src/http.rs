@@ -0,0 +1,182 @@
+//! HTTP client for registries, configured like cargo.
+
+use std::{
+    env,
+    fs::read_to_string,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use once_cell::sync::OnceCell;
+use reqwest::{header::HeaderMap, Client, Proxy, Response, StatusCode};
+use tokio::time::sleep;
+
+const USER_AGENT: &str = concat!(
+    "cargo-mono/",
+    env!("CARGO_PKG_VERSION"),
+    " (",
+    env!("CARGO_PKG_REPOSITORY"),
+    ")"
+);
+
+/// Number of retries for transient errors.
+const MAX_RETRIES: u32 = 3;
+
+/// HTTP client shared by all requests to registries.
+#[derive(Debug)]
+pub struct RegistryClient {
+    client: Client,
+}
+
+/// Returns the shared client, which is configured using `[http]` of cargo
+/// config files and `CARGO_HTTP_*` environment variables.
+pub fn client() -> Result<&'static RegistryClient> {
+    static CLIENT: OnceCell<RegistryClient> = OnceCell::new();
+
+    CLIENT.get_or_try_init(|| RegistryClient::new(&HttpConfig::load()))
+}
+
+impl RegistryClient {
+    fn new(config: &HttpConfig) -> Result<Self> {
+        let mut builder = Client::builder()
+            .user_agent(USER_AGENT)
+            .connect_timeout(config.timeout)
+            .timeout(config.timeout);
+
+        if let Some(proxy) = &config.proxy {
+            builder = builder
+                .proxy(Proxy::all(proxy).with_context(|| format!("invalid proxy `{}`", proxy))?);
+        }
+
+        Ok(RegistryClient {
+            client: builder.build().context("failed to build HTTP client")?,
+        })
+    }
+
+    /// Sends a `GET` request, retrying on timeouts, connection errors, `429`
+    /// and `5xx`.
+    ///
+    /// Other statuses are returned as-is, so callers can handle `404`.
+    pub async fn get(&self, url: &str, headers: HeaderMap) -> Result<Response> {
+        let mut attempt = 0;
+
+        loop {
+            let res = self.client.get(url).headers(headers.clone()).send().await;
+
+            let retryable = match &res {
+                Ok(res) => {
+                    res.status() == StatusCode::TOO_MANY_REQUESTS || res.status().is_server_error()
+                }
+                Err(err) => err.is_timeout() || err.is_connect(),
+            };
+
+            if !retryable || attempt >= MAX_RETRIES {
+                return res.with_context(|| format!("failed to send a request to {}", url));
+            }
+
+            attempt += 1;
+            let delay = Duration::from_secs(2u64.pow(attempt - 1));
+            match &res {
+                Ok(res) => eprintln!(
+                    "{} responded with {}; retrying in {}s",
+                    url,
+                    res.status(),
+                    delay.as_secs()
+                ),
+                Err(err) => eprintln!(
+                    "Request to {} failed: {}; retrying in {}s",
+                    url,
+                    err,
+                    delay.as_secs()
+                ),
+            }
+            sleep(delay).await;
+        }
+    }
+}
+
+/// `[http]` section of cargo config.
+#[derive(Debug)]
+struct HttpConfig {
+    proxy: Option<String>,
+    timeout: Duration,
+}
+
+impl HttpConfig {
+    /// Environment variables take precedence over config files, and config
+    /// files closer to the current directory take precedence over others.
+    ///
+    /// If no proxy is configured, `HTTPS_PROXY` and friends are used by
+    /// `reqwest`.
+    fn load() -> Self {
+        let mut proxy = env::var("CARGO_HTTP_PROXY").ok();
+        let mut timeout = env::var("CARGO_HTTP_TIMEOUT")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok());
+
+        for file in config_files() {
+            let http = match read_to_string(&file)
+                .ok()
+                .and_then(|s| s.parse::<toml::Value>().ok())
+                .and_then(|v| v.get("http").cloned())
+            {
+                Some(v) => v,
+                None => continue,
+            };
+
+            if proxy.is_none() {
+                proxy = http
+                    .get("proxy")
+                    .and_then(|v| v.as_str())
+                    .map(|v| v.to_string());
+            }
+            if timeout.is_none() {
+                timeout = http
+                    .get("timeout")
+                    .and_then(|v| v.as_integer())
+                    .map(|v| v as u64);
+            }
+        }
+
+        HttpConfig {
+            proxy: proxy.filter(|v| !v.is_empty()),
+            timeout: Duration::from_secs(timeout.unwrap_or(30)),
+        }
+    }
+}
+
+/// Cargo config files, from the most specific one.
+pub fn config_files() -> Vec<PathBuf> {
+    let mut dirs = vec![];
+    if let Ok(cwd) = env::current_dir() {
+        dirs.extend(cwd.ancestors().map(|dir| dir.join(".cargo")));
+    }
+    if let Some(home) = cargo_home() {
+        dirs.push(home);
+    }
+
+    let mut files = vec![];
+    for dir in dirs {
+        for name in ["config.toml", "config"] {
+            let file = dir.join(name);
+            if file.is_file() && !files.contains(&file) {
+                files.push(file);
+                break;
+            }
+        }
+    }
+
+    files
+}
+
+/// `$CARGO_HOME`, or `~/.cargo` if it's not set.
+pub fn cargo_home() -> Option<PathBuf> {
+    if let Some(home) = env::var_os("CARGO_HOME") {
+        return Some(PathBuf::from(home));
+    }
+
+    env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .map(|home| Path::new(&home).join(".cargo"))
+}
@@ -0,0 +1,105 @@
+//! Disk cache of index files fetched from registries.
+
+use std::{
+    fs::{create_dir_all, read, read_to_string, write},
+    path::PathBuf,
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::http::cargo_home;
+
+/// Index file of a crate stored on disk, so that later runs can use
+/// conditional requests or work offline.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedIndex {
+    /// URL of the index file.
+    pub url: String,
+    /// `ETag` of the response, used for `If-None-Match`.
+    pub etag: Option<String>,
+    /// `Last-Modified` of the response, used for `If-Modified-Since`.
+    pub last_modified: Option<String>,
+    /// Content of the index file.
+    pub body: String,
+}
+
+/// `$CARGO_HOME/cache/cargo-mono/index`
+fn cache_dir() -> Option<PathBuf> {
+    cargo_home().map(|home| home.join("cache").join("cargo-mono").join("index"))
+}
+
+fn cache_file(url: &str) -> Option<PathBuf> {
+    let hash = hex::encode(Sha256::digest(url.as_bytes()));
+
+    cache_dir().map(|dir| dir.join(format!("{}.json", &hash[..16])))
+}
+
+/// Returns [None] if `url` is not cached or the cache is broken.
+pub fn load(url: &str) -> Option<CachedIndex> {
+    let content = read_to_string(cache_file(url)?).ok()?;
+    let cached = serde_json::from_str::<CachedIndex>(&content).ok()?;
+
+    if cached.url != url {
+        return None;
+    }
+
+    Some(cached)
+}
+
+/// Saves `cached`, overwriting the previous cache of the same URL.
+pub fn store(cached: &CachedIndex) -> Result<()> {
+    let path = match cache_file(&cached.url) {
+        Some(v) => v,
+        None => return Ok(()),
+    };
+
+    if let Some(dir) = path.parent() {
+        create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    }
+
+    let content = serde_json::to_string(cached).context("failed to serialize index")?;
+    write(&path, content).with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Reads the index file of crates.io cached by cargo itself, at
+/// `$CARGO_HOME/registry/index/index.crates.io-*/.cache/{path}`.
+///
+/// Returns the index entries as lines of JSON.
+pub fn load_from_cargo_cache(path: &str) -> Option<String> {
+    let index_dir = cargo_home()?.join("registry").join("index");
+
+    for entry in index_dir.read_dir().ok()? {
+        let entry = entry.ok()?;
+        if !entry
+            .file_name()
+            .to_string_lossy()
+            .starts_with("index.crates.io-")
+        {
+            continue;
+        }
+
+        let content = match read(entry.path().join(".cache").join(path)) {
+            Ok(v) => v,
+            Err(..) => continue,
+        };
+
+        // The file starts with a version byte and a `u32` index format version,
+        // followed by the header and pairs of version and JSON, separated by `\0`.
+        if content.len() < 5 {
+            continue;
+        }
+
+        let lines = content[5..]
+            .split(|b| *b == 0)
+            .skip(1)
+            .filter(|s| s.starts_with(b"{"))
+            .map(String::from_utf8_lossy)
+            .collect::<Vec<_>>();
+
+        return Some(lines.join("\n"));
+    }
+
+    None
+}
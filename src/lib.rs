@@ -0,0 +1,34 @@
+//! Library behind `cargo mono`, a cargo subcommand to manage versions and
+//! releases of crates in a mono repository.
+//!
+//! The binary is a thin front end over the following modules, which can be
+//! used to build other release tools:
+//!
+//! - [cargo_workspace]: Loading the workspace and selecting crates.
+//! - [bump] and [plan]: Computing new versions of crates and their dependants.
+//! - [manifest] and [requirements]: Editing `Cargo.toml` files.
+//! - [registry], [crates_io] and [local_registry]: Querying registries.
+//! - [publish] and [check]: Publishing crates in dependency order.
+//! - [graph] and [list]: Inspecting the workspace.
+
+#![warn(missing_docs)]
+
+pub mod bump;
+pub mod cargo_workspace;
+pub mod check;
+pub mod config;
+pub mod crates_io;
+pub mod filter;
+pub mod graph;
+mod http;
+mod index_cache;
+pub mod list;
+pub mod local_registry;
+pub mod lockfile;
+pub mod manifest;
+pub mod message;
+pub mod plan;
+pub mod publish;
+pub mod registry;
+pub mod requirements;
+pub mod summary;
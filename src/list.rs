@@ -0,0 +1,233 @@
+//! `cargo mono list`, which prints versions and publish status of workspace
+//! members.
+
+use std::{cmp::Ordering, path::Path};
+
+use anyhow::{bail, Result};
+use cargo_metadata::{DependencyKind, Package};
+use clap::{Args, ValueEnum};
+use petgraph::{algo::toposort, graphmap::DiGraphMap};
+use semver::Version;
+use serde::Serialize;
+
+use crate::{
+    cargo_workspace::Workspace,
+    registry::{prefetch_published_versions, Registry, RegistryArgs},
+    summary::markdown_table,
+};
+
+/// Lists workspace members with their local and published versions.
+#[derive(Debug, Args)]
+pub struct ListCommand {
+    /// Output format.
+    #[clap(long, value_enum, default_value_t)]
+    pub format: ListFormat,
+
+    /// Order of crates.
+    #[clap(long, value_enum, default_value_t)]
+    pub sort: ListOrder,
+
+    /// Registry to read published versions from.
+    #[clap(flatten)]
+    pub registry: RegistryArgs,
+}
+
+/// Output format of `cargo mono list`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ListFormat {
+    /// A table, which is also valid Markdown.
+    #[default]
+    Table,
+    /// A JSON array.
+    Json,
+}
+
+/// Order of crates printed by `cargo mono list`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ListOrder {
+    /// Sorted by name.
+    #[default]
+    Name,
+    /// Dependencies first, ignoring dev-dependencies.
+    Topological,
+}
+
+/// Publish status of a crate, comparing the local version to the registry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ListStatus {
+    /// The local version is not published yet.
+    Ahead,
+    /// The local version is the latest published version.
+    Equal,
+    /// The registry has a version newer than the local one.
+    Behind,
+    /// No version of the crate is published.
+    Unpublished,
+    /// The crate can't be published to the registry.
+    Private,
+}
+
+impl ListStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            ListStatus::Ahead => "ahead",
+            ListStatus::Equal => "equal",
+            ListStatus::Behind => "behind",
+            ListStatus::Unpublished => "unpublished",
+            ListStatus::Private => "private",
+        }
+    }
+}
+
+/// A row of `cargo mono list`.
+#[derive(Debug, Serialize)]
+pub struct ListEntry {
+    /// Name of the crate.
+    pub name: String,
+    /// Directory of the crate, relative to the workspace root.
+    pub path: String,
+    /// The local version.
+    pub version: Version,
+    /// The latest version on the registry, or `None` if the crate is not
+    /// published or private.
+    pub registry_version: Option<Version>,
+    /// Publish status.
+    pub status: ListStatus,
+    /// Why the crate can't be published.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl ListCommand {
+    /// Runs the command in the current workspace.
+    pub async fn run(&self) -> Result<()> {
+        let ws = Workspace::load().await?;
+        let packages = match self.sort {
+            ListOrder::Name => ws.members,
+            ListOrder::Topological => sort_topologically(&ws.members)?,
+        };
+
+        let registry = self.registry.build()?;
+        let entries = self.entries(&*registry, &ws.root, &packages).await?;
+
+        let output = match self.format {
+            ListFormat::Table => to_table(&entries),
+            ListFormat::Json => serde_json::to_string_pretty(&entries)? + "\n",
+        };
+        print!("{}", output);
+
+        Ok(())
+    }
+
+    /// Computes the status of `packages`, keeping the order.
+    pub async fn entries(
+        &self,
+        registry: &dyn Registry,
+        ws_root: &Path,
+        packages: &[Package],
+    ) -> Result<Vec<ListEntry>> {
+        let reason = |p: &Package| self.registry.unpublishable_reason(p);
+
+        prefetch_published_versions(
+            registry,
+            packages
+                .iter()
+                .filter(|p| reason(p).is_none())
+                .map(|p| &*p.name),
+        )
+        .await?;
+
+        let mut entries = vec![];
+        for p in packages {
+            let dir = p.manifest_path.parent().unwrap().as_std_path();
+            let path = match dir.strip_prefix(ws_root) {
+                Ok(v) if v.as_os_str().is_empty() => ".".to_string(),
+                Ok(v) => v.display().to_string(),
+                Err(_) => dir.display().to_string(),
+            };
+
+            let reason = reason(p);
+            let registry_version = match reason {
+                Some(_) => None,
+                None => Some(registry.published_version(&p.name, true).await?)
+                    .filter(|v| *v != Version::new(0, 0, 0)),
+            };
+
+            let status = match (&reason, &registry_version) {
+                (Some(_), _) => ListStatus::Private,
+                (None, None) => ListStatus::Unpublished,
+                (None, Some(v)) => match p.version.cmp(v) {
+                    Ordering::Greater => ListStatus::Ahead,
+                    Ordering::Equal => ListStatus::Equal,
+                    Ordering::Less => ListStatus::Behind,
+                },
+            };
+
+            entries.push(ListEntry {
+                name: p.name.clone(),
+                path,
+                version: p.version.clone(),
+                registry_version,
+                status,
+                reason,
+            });
+        }
+
+        Ok(entries)
+    }
+}
+
+/// Sorts `packages` so that dependencies come before their dependants.
+///
+/// Dev-dependencies are ignored, as they are allowed to form cycles.
+fn sort_topologically(packages: &[Package]) -> Result<Vec<Package>> {
+    let mut graph = DiGraphMap::<usize, ()>::new();
+    for (i, p) in packages.iter().enumerate() {
+        graph.add_node(i);
+
+        for dep in &p.dependencies {
+            if dep.kind == DependencyKind::Development {
+                continue;
+            }
+            if let Some(dep) = packages.iter().position(|p| p.name == dep.name) {
+                graph.add_edge(dep, i, ());
+            }
+        }
+    }
+
+    let order = match toposort(&graph, None) {
+        Ok(v) => v,
+        Err(e) => bail!(
+            "circular dependency detected: {}",
+            packages[e.node_id()].name
+        ),
+    };
+
+    Ok(order.into_iter().map(|i| packages[i].clone()).collect())
+}
+
+fn to_table(entries: &[ListEntry]) -> String {
+    let mut rows = vec![[
+        "Crate".to_string(),
+        "Path".to_string(),
+        "Version".to_string(),
+        "Registry".to_string(),
+        "Status".to_string(),
+    ]];
+
+    for e in entries {
+        rows.push([
+            e.name.clone(),
+            e.path.clone(),
+            e.version.to_string(),
+            e.registry_version
+                .as_ref()
+                .map(|v| v.to_string())
+                .unwrap_or_else(|| "-".into()),
+            e.status.as_str().to_string(),
+        ]);
+    }
+
+    markdown_table(&rows)
+}
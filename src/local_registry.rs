@@ -0,0 +1,220 @@
+//! A directory-backed registry used instead of crates.io for testing.
+
+use std::{
+    fs::{copy, create_dir_all, read, read_dir, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use cargo_metadata::{DependencyKind, MetadataCommand, Package};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use tokio::{process::Command, task::spawn_blocking};
+
+use crate::{
+    crates_io::{index_path, is_stripped_on_publish},
+    http::cargo_home,
+    registry::{Registry, SparseIndex},
+};
+
+/// A directory which works as a registry, used instead of crates.io for
+/// testing.
+///
+/// The directory uses the layout of cargo's `local-registry` source, so cargo
+/// can resolve dependencies published to it:
+///
+/// - `index/` contains the index files, in the same layout as crates.io.
+/// - `{name}-{version}.crate` are the packaged crates.
+///
+/// As the registry replaces crates.io, dependencies from crates.io should be
+/// copied to it using [LocalRegistry::vendor_dependencies].
+#[derive(Debug, Clone)]
+pub struct LocalRegistry {
+    root: PathBuf,
+}
+
+impl LocalRegistry {
+    /// Uses `root` as a registry, creating it if it does not exist.
+    pub fn new(root: &Path) -> Result<Self> {
+        create_dir_all(root.join("index"))
+            .with_context(|| format!("failed to create {}", root.display()))?;
+        let root = root
+            .canonicalize()
+            .with_context(|| format!("failed to resolve {}", root.display()))?;
+
+        Ok(LocalRegistry { root })
+    }
+
+    /// URL of the index, which can be passed to
+    /// [crate::registry::SparseIndex::new].
+    pub fn index_url(&self) -> String {
+        format!("file://{}", self.root.join("index").display())
+    }
+
+    /// Arguments for cargo to use this registry instead of crates.io.
+    pub fn cargo_args(&self) -> Vec<String> {
+        vec![
+            "--config".into(),
+            "source.crates-io.replace-with = \"mono-local\"".into(),
+            "--config".into(),
+            format!(
+                "source.mono-local.local-registry = {:?}",
+                self.root.display().to_string()
+            ),
+        ]
+    }
+
+    /// Copies dependencies of the current workspace which are downloaded from
+    /// crates.io to the registry, so that cargo can resolve them while
+    /// packaging workspace members.
+    ///
+    /// `.crate` files are taken from the download cache of cargo.
+    pub async fn vendor_dependencies(&self) -> Result<()> {
+        let packages = spawn_blocking(|| {
+            MetadataCommand::new()
+                .exec()
+                .context("failed to run `cargo metadata`")
+        })
+        .await
+        .expect("failed to fetch metadata")?
+        .packages;
+
+        let index = SparseIndex::new(&self.index_url());
+        for p in packages {
+            if !p.source.as_ref().map_or(false, |s| s.is_crates_io()) {
+                continue;
+            }
+
+            let is_vendored = index
+                .fetch_versions(&p.name)
+                .await?
+                .iter()
+                .any(|v| v.version == p.version);
+            if is_vendored {
+                continue;
+            }
+
+            let file_name = format!("{}-{}.crate", p.name, p.version);
+            let cached = find_in_download_cache(&file_name).with_context(|| {
+                format!(
+                    "`{}` is not in the download cache of cargo; run `cargo fetch` first",
+                    file_name
+                )
+            })?;
+
+            self.add(&p, &cached)?;
+        }
+
+        Ok(())
+    }
+
+    /// Packages `p` and adds it to the registry, like `cargo publish`.
+    ///
+    /// `target_dir` is the target directory of the workspace of `p`.
+    pub async fn publish(&self, p: &Package, no_verify: bool, target_dir: &Path) -> Result<()> {
+        eprintln!("Publishing `{}` to {}", p.name, self.root.display());
+
+        let mut cmd = Command::new("cargo");
+        cmd.arg("package")
+            .args(self.cargo_args())
+            .arg("--allow-dirty")
+            .arg("--manifest-path")
+            .arg(&p.manifest_path);
+        if no_verify {
+            cmd.arg("--no-verify");
+        }
+
+        let output = cmd
+            .output()
+            .await
+            .context("failed to run `cargo package`")?;
+        if !output.status.success() {
+            bail!(
+                "`cargo package` failed:\n{}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+
+        let file_name = format!("{}-{}.crate", p.name, p.version);
+        let packaged = target_dir.join("package").join(&file_name);
+
+        self.add(p, &packaged)
+    }
+
+    /// Copies `crate_file`, which is the `.crate` file of `p`, to the registry
+    /// and adds `p` to the index.
+    fn add(&self, p: &Package, crate_file: &Path) -> Result<()> {
+        let file_name = format!("{}-{}.crate", p.name, p.version);
+        let content =
+            read(crate_file).with_context(|| format!("failed to read {}", crate_file.display()))?;
+
+        copy(crate_file, self.root.join(&file_name))
+            .with_context(|| format!("failed to copy {}", crate_file.display()))?;
+
+        let entry = index_entry(p, &hex::encode(Sha256::digest(&content)));
+        let index_file = self.root.join("index").join(index_path(&p.name));
+        if let Some(dir) = index_file.parent() {
+            create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&index_file)
+            .with_context(|| format!("failed to open {}", index_file.display()))?;
+        writeln!(file, "{}", entry)
+            .with_context(|| format!("failed to write {}", index_file.display()))?;
+
+        Ok(())
+    }
+}
+
+/// Finds a `.crate` file in `$CARGO_HOME/registry/cache/*/`.
+fn find_in_download_cache(file_name: &str) -> Option<PathBuf> {
+    let cache = cargo_home()?.join("registry").join("cache");
+
+    read_dir(cache)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path().join(file_name))
+        .find(|path| path.is_file())
+}
+
+/// Builds a line of the index for `p`, in the format of the sparse index.
+fn index_entry(p: &Package, cksum: &str) -> serde_json::Value {
+    let deps = p
+        .dependencies
+        .iter()
+        .filter(|dep| !is_stripped_on_publish(dep))
+        .map(|dep| {
+            let kind = match dep.kind {
+                DependencyKind::Development => "dev",
+                DependencyKind::Build => "build",
+                _ => "normal",
+            };
+
+            json!({
+                "name": dep.rename.as_ref().unwrap_or(&dep.name),
+                "req": dep.req.to_string(),
+                "features": dep.features,
+                "optional": dep.optional,
+                "default_features": dep.uses_default_features,
+                "target": dep.target.as_ref().map(|t| t.to_string()),
+                "kind": kind,
+                "registry": dep.registry,
+                "package": dep.rename.as_ref().map(|_| &dep.name),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    json!({
+        "name": p.name,
+        "vers": p.version.to_string(),
+        "deps": deps,
+        "cksum": cksum,
+        "features": p.features,
+        "yanked": false,
+        "links": p.links,
+    })
+}
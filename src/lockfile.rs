@@ -0,0 +1,134 @@
+//! Updating `Cargo.lock` after manifests are modified.
+
+use std::{collections::BTreeMap, fmt, fs::read_to_string, path::Path, process::Output};
+
+use anyhow::{bail, Context, Result};
+use tokio::process::Command;
+
+/// An entry of `Cargo.lock` whose versions were changed by
+/// [update_lockfile].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockChange {
+    /// Name of the package.
+    pub name: String,
+    /// Versions locked before the update.
+    pub old: Vec<String>,
+    /// Versions locked after the update.
+    pub new: Vec<String>,
+}
+
+impl fmt::Display for LockChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let list = |v: &[String]| {
+            if v.is_empty() {
+                "(none)".to_string()
+            } else {
+                v.join(", ")
+            }
+        };
+
+        write!(
+            f,
+            "{}: {} -> {}",
+            self.name,
+            list(&self.old),
+            list(&self.new)
+        )
+    }
+}
+
+/// Refreshes lock entries of workspace members in `Cargo.lock` and returns the
+/// entries which are changed.
+///
+/// This runs `cargo update --workspace` offline first, and falls back to the
+/// network if the offline update fails, unless `offline` is true.
+pub async fn update_lockfile(ws_root: &Path, offline: bool) -> Result<Vec<LockChange>> {
+    let lock_path = ws_root.join("Cargo.lock");
+    let before = read_lock_entries(&lock_path)?;
+
+    let mut output = cargo_update(ws_root, true).await?;
+    if !output.status.success() && !offline {
+        eprintln!("Offline update of `Cargo.lock` failed; retrying with network access");
+
+        output = cargo_update(ws_root, false).await?;
+    }
+    if !output.status.success() {
+        bail!(
+            "`cargo update --workspace` failed with {}:\n{}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    let after = read_lock_entries(&lock_path)?;
+
+    let mut changes = vec![];
+    for (key, new) in &after {
+        let old = before.get(key).cloned().unwrap_or_default();
+        if old != *new {
+            changes.push(LockChange {
+                name: key.0.clone(),
+                old,
+                new: new.clone(),
+            });
+        }
+    }
+    for (key, old) in &before {
+        if !after.contains_key(key) {
+            changes.push(LockChange {
+                name: key.0.clone(),
+                old: old.clone(),
+                new: vec![],
+            });
+        }
+    }
+    changes.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(changes)
+}
+
+async fn cargo_update(ws_root: &Path, offline: bool) -> Result<Output> {
+    let mut cmd = Command::new("cargo");
+    cmd.arg("update")
+        .arg("--workspace")
+        .arg("--manifest-path")
+        .arg(ws_root.join("Cargo.toml"));
+
+    if offline {
+        cmd.arg("--offline");
+    }
+
+    cmd.output().await.context("failed to run `cargo update`")
+}
+
+/// Returns versions of each package in `Cargo.lock`, keyed by `(name, source)`.
+fn read_lock_entries(path: &Path) -> Result<BTreeMap<(String, String), Vec<String>>> {
+    let mut entries = BTreeMap::<_, Vec<_>>::new();
+
+    if !path.exists() {
+        return Ok(entries);
+    }
+
+    let content =
+        read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let doc = content
+        .parse::<toml_edit::Document>()
+        .with_context(|| format!("{} is invalid", path.display()))?;
+
+    if let Some(packages) = doc["package"].as_array_of_tables() {
+        for p in packages.iter() {
+            let field = |key: &str| {
+                p.get(key)
+                    .and_then(|v| v.as_str())
+                    .unwrap_or_default()
+                    .to_string()
+            };
+
+            let versions = entries.entry((field("name"), field("source"))).or_default();
+            versions.push(field("version"));
+            versions.sort();
+        }
+    }
+
+    Ok(entries)
+}
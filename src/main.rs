@@ -1,20 +1,18 @@
 use std::env;
 
 use anyhow::{Context, Result};
-use bump::BumpCommand;
+use cargo_mono::{
+    bump::BumpCommand, graph::GraphCommand, list::ListCommand, publish::PublishCommand,
+};
 use clap::Parser;
-use publish::PublishCommand;
-
-mod bump;
-mod cargo_workspace;
-mod crates_io;
-mod publish;
 
 #[derive(Debug, Parser)]
 #[clap(author, about)]
 enum Command {
     Bump(BumpCommand),
     Publish(PublishCommand),
+    Graph(GraphCommand),
+    List(ListCommand),
 }
 
 #[tokio::main]
@@ -40,6 +38,14 @@ async fn main() -> Result<()> {
         Command::Publish(cmd) => {
             cmd.run().await.context("failed to publish")?;
         }
+        Command::Graph(cmd) => {
+            cmd.run()
+                .await
+                .context("failed to print the dependency graph")?;
+        }
+        Command::List(cmd) => {
+            cmd.run().await.context("failed to list crates")?;
+        }
     }
     Ok(())
 }
@@ -0,0 +1,200 @@
+//! Editing `Cargo.toml` files while preserving formatting.
+
+use std::{
+    collections::HashMap,
+    fs::{read_to_string, write},
+    path::Path,
+    sync::Arc,
+};
+
+use anyhow::{bail, Context, Result};
+use cargo_metadata::Package;
+use semver::Version;
+use tokio::task::spawn_blocking;
+use toml_edit::{Item, Table, Value};
+
+use crate::message::{Event, MessageFormat};
+
+/// Modifies `Cargo.toml` of `package`.
+///
+/// If `new_version` is [Some], the version of the package is changed. Each
+/// requirement on a crate in `deps_to_bump` is changed to the given version.
+///
+/// [Event::ManifestPatched] is emitted using `message_format` after writing.
+pub async fn patch(
+    package: Package,
+    new_version: Option<Version>,
+    deps_to_bump: Arc<HashMap<String, Version>>,
+    message_format: MessageFormat,
+) -> Result<()> {
+    match &new_version {
+        Some(v) => eprintln!("Package({}) -> {}", package.name, v),
+        None => eprintln!("Package({}): updating requirements", package.name),
+    }
+
+    let manifest_path = package.manifest_path.clone();
+    let version = new_version.clone();
+    let deps = deps_to_bump.clone();
+    spawn_blocking(move || -> Result<_> {
+        let toml = read_to_string(&manifest_path).context("failed to read error")?;
+
+        let mut doc = toml
+            .parse::<toml_edit::Document>()
+            .context("toml file is invalid")?;
+
+        if let Some(v) = version {
+            // Bump version of package itself
+            doc["package"]["version"] = toml_edit::value(v.to_string());
+        }
+
+        // Bump version of dependencies
+        patch_dep_tables(doc.as_table_mut(), &deps)?;
+
+        if let Some(targets) = doc["target"].as_table_mut() {
+            for (target, item) in targets.iter_mut() {
+                if let Some(table) = item.as_table_mut() {
+                    patch_dep_tables(table, &deps)
+                        .with_context(|| format!("failed to patch target.{}", target))?;
+                }
+            }
+        }
+
+        write(&manifest_path, doc.to_string()).context("failed to save modified Cargo.toml")?;
+
+        Ok(())
+    })
+    .await
+    .expect("failed to edit toml file")?;
+
+    message_format.emit(&Event::ManifestPatched {
+        package: &package.name,
+        old_version: package.version.to_string(),
+        new_version: new_version.as_ref().map(|v| v.to_string()),
+        requirements: package
+            .dependencies
+            .iter()
+            .filter_map(|dep| {
+                deps_to_bump
+                    .get(&dep.name)
+                    .map(|v| (&*dep.name, v.to_string()))
+            })
+            .collect(),
+    });
+
+    Ok(())
+}
+
+/// Changes requirements in `[workspace.dependencies]` of the root manifest.
+///
+/// Members using `{ workspace = true }` inherit requirements from there, so
+/// [patch] leaves them as is.
+pub async fn patch_workspace_deps(
+    ws_root: &Path,
+    deps_to_bump: Arc<HashMap<String, Version>>,
+) -> Result<()> {
+    let manifest_path = ws_root.join("Cargo.toml");
+
+    spawn_blocking(move || -> Result<_> {
+        let toml = read_to_string(&manifest_path)
+            .with_context(|| format!("failed to read {}", manifest_path.display()))?;
+
+        let mut doc = toml
+            .parse::<toml_edit::Document>()
+            .context("toml file is invalid")?;
+
+        let table = match doc["workspace"]["dependencies"].as_table_mut() {
+            Some(table) => table,
+            None => return Ok(()),
+        };
+
+        if patch_deps(table, "workspace.dependencies", &deps_to_bump)? {
+            write(&manifest_path, doc.to_string()).context("failed to save modified Cargo.toml")?;
+        }
+
+        Ok(())
+    })
+    .await
+    .expect("failed to edit toml file")
+}
+
+/// Patches `dependencies`, `dev-dependencies` and `build-dependencies` of
+/// `table`, which is the root of a manifest or a `[target.'cfg(..)']` table.
+fn patch_dep_tables(table: &mut Table, deps_to_bump: &HashMap<String, Version>) -> Result<()> {
+    for &dep_type in &["dependencies", "dev-dependencies", "build-dependencies"] {
+        if let Some(deps) = table.get_mut(dep_type).and_then(|v| v.as_table_mut()) {
+            patch_deps(deps, dep_type, deps_to_bump)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Changes the version requirement of each dependency in `deps_to_bump`.
+///
+/// Dependencies are matched by the name of the package, so renamed
+/// dependencies like `foo = { package = "bar" }` are handled. Returns true if
+/// anything is changed.
+fn patch_deps(
+    deps: &mut Table,
+    dep_type: &str,
+    deps_to_bump: &HashMap<String, Version>,
+) -> Result<bool> {
+    let mut changed = false;
+
+    for (key, prev) in deps.iter_mut() {
+        let package_name = match &*prev {
+            Item::Value(Value::InlineTable(t)) => t.get("package").and_then(|v| v.as_str()),
+            Item::Table(t) => t.get("package").and_then(|v| v.as_str()),
+            _ => None,
+        }
+        .unwrap_or(key);
+
+        let new_version = match deps_to_bump.get(package_name) {
+            Some(v) => toml_edit::value(v.to_string()),
+            None => continue,
+        };
+
+        // We should handle object like
+        //
+        // { version = "0.1", path = "./macros" }
+        match prev {
+            Item::None => {
+                unreachable!("{}.{} cannot be none", dep_type, key)
+            }
+            Item::Value(v) => match v {
+                Value::String(_) => *v = new_version.as_value().unwrap().clone(),
+                Value::InlineTable(t) => {
+                    // { workspace = true }
+                    if t.get("workspace").and_then(|v| v.as_bool()) == Some(true) {
+                        continue;
+                    }
+
+                    // { path = "../foo" }, e.g. a dev-dependency which is
+                    // not published.
+                    match t.get_mut("version") {
+                        Some(v) => *v = new_version.as_value().unwrap().clone(),
+                        None => continue,
+                    }
+                }
+                _ => bail!("{}.{}: cannot be unknown type {:?}", dep_type, key, v),
+            },
+            Item::Table(t) => {
+                // [dependencies.foo]
+                // version = "0.1"
+                // path = "./foo"
+                if t.contains_key("version") {
+                    t["version"] = new_version;
+                } else {
+                    continue;
+                }
+            }
+            Item::ArrayOfTables(_) => {
+                bail!("{}.{} cannot be array of table", dep_type, key)
+            }
+        }
+
+        changed = true;
+    }
+
+    Ok(changed)
+}
@@ -0,0 +1,111 @@
+//! Machine-readable messages printed with `--message-format json`.
+
+use std::collections::BTreeMap;
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+/// Format of messages for other programs, selected by `--message-format`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum MessageFormat {
+    /// Only human-oriented logs are printed to stderr.
+    #[default]
+    Human,
+    /// Each [Event] is printed to stdout as a line of JSON.
+    Json,
+}
+
+impl MessageFormat {
+    /// Returns true if `--message-format json` is used.
+    pub fn is_json(self) -> bool {
+        self == MessageFormat::Json
+    }
+
+    /// Prints `event` to stdout if `--message-format json` is used.
+    pub fn emit(self, event: &Event) {
+        if self.is_json() {
+            println!(
+                "{}",
+                serde_json::to_string(event).expect("failed to serialize event")
+            );
+        }
+    }
+}
+
+/// Machine-readable events of `bump` and `publish`.
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum Event<'a> {
+    /// New versions are determined by `bump`.
+    PlanComputed {
+        /// Crates to bump, sorted by name.
+        crates: Vec<PlannedVersion<'a>>,
+    },
+    /// `Cargo.toml` of a crate is written.
+    ManifestPatched {
+        /// Name of the crate.
+        package: &'a str,
+        /// The version before patching.
+        old_version: String,
+        /// The new version, or [None] if only requirements are changed.
+        new_version: Option<String>,
+        /// New requirements on workspace members, keyed by their names.
+        requirements: BTreeMap<&'a str, String>,
+    },
+    /// Files modified by `bump` are restored, as it failed after writing
+    /// them.
+    RolledBack {
+        /// Paths of the restored files.
+        files: Vec<String>,
+        /// The error which caused the rollback.
+        reason: String,
+    },
+    /// A crate is published.
+    Published {
+        /// Name of the crate.
+        package: &'a str,
+        /// The published version.
+        version: String,
+        /// The latest version before publishing, or `0.0.0` if it's the first
+        /// release.
+        published_version: String,
+    },
+    /// A crate is not published, as it's up to date, excluded, or not
+    /// publishable.
+    Skipped {
+        /// Name of the crate.
+        package: &'a str,
+        /// The local version.
+        version: String,
+        /// The latest version on the registry, or [None] if the registry is
+        /// not queried.
+        published_version: Option<String>,
+        /// `up-to-date`, `excluded` or `not-publishable`.
+        reason: &'a str,
+        /// Why the crate can't be published.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        detail: Option<String>,
+    },
+    /// Publishing a crate failed.
+    Failed {
+        /// Name of the crate.
+        package: &'a str,
+        /// The version which failed to be published.
+        version: String,
+        /// The error.
+        reason: String,
+    },
+}
+
+/// New version of a crate in [Event::PlanComputed].
+#[derive(Debug, Serialize)]
+pub struct PlannedVersion<'a> {
+    /// Name of the crate.
+    pub package: &'a str,
+    /// The local version before bumping.
+    pub old_version: String,
+    /// The bumped version.
+    pub new_version: String,
+    /// True if the change is breaking.
+    pub breaking: bool,
+}
@@ -0,0 +1,147 @@
+//! Plans describing versions to bump, which can be saved and applied later.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{read_to_string, write},
+    path::Path,
+};
+
+use anyhow::{bail, Context, Result};
+use cargo_metadata::Package;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+/// Versions decided by `cargo mono bump`.
+///
+/// This can be saved using `--save-plan` and applied later using `--plan`,
+/// without prompting.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct BumpPlan {
+    /// New versions, keyed by the name of the crate.
+    #[serde(default, rename = "crate")]
+    pub crates: BTreeMap<String, PlannedBump>,
+}
+
+/// An entry of [BumpPlan].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannedBump {
+    /// The new version of the crate.
+    pub version: Version,
+
+    /// True if it's a breaking change.
+    #[serde(default)]
+    pub breaking: bool,
+
+    /// Dependants selected to be bumped along with the crate.
+    #[serde(default)]
+    pub dependants: Vec<String>,
+}
+
+impl BumpPlan {
+    /// Reads a plan saved using [BumpPlan::save].
+    pub fn load(path: &Path) -> Result<Self> {
+        let content =
+            read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+
+        toml::from_str(&content).with_context(|| format!("{} is not a valid plan", path.display()))
+    }
+
+    /// Writes the plan to `path` as TOML.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = toml::to_string_pretty(self).context("failed to serialize plan")?;
+
+        write(path, content).with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    /// Merges `other` into `self`, so that each crate has exactly one new
+    /// version.
+    ///
+    /// The higher version wins, and selected dependants are combined.
+    pub fn merge(&mut self, other: BumpPlan) {
+        for (name, bump) in other.crates {
+            match self.crates.get_mut(&name) {
+                Some(prev) => {
+                    if bump.version > prev.version {
+                        prev.version = bump.version;
+                    }
+                    prev.breaking |= bump.breaking;
+
+                    for dependant in bump.dependants {
+                        if !prev.dependants.contains(&dependant) {
+                            prev.dependants.push(dependant);
+                        }
+                    }
+                }
+                None => {
+                    self.crates.insert(name, bump);
+                }
+            }
+        }
+    }
+
+    /// New version of each crate in the plan.
+    pub fn versions(&self) -> HashMap<String, Version> {
+        self.crates
+            .iter()
+            .map(|(name, bump)| (name.clone(), bump.version.clone()))
+            .collect()
+    }
+
+    /// Checks if the plan can be applied to `packages`.
+    ///
+    /// `published` should contain the published version of each crate in the
+    /// plan.
+    pub fn validate(
+        &self,
+        packages: &[Package],
+        published: &HashMap<String, Version>,
+    ) -> Result<()> {
+        let mut errors = vec![];
+
+        for (name, bump) in &self.crates {
+            let package = match packages.iter().find(|p| p.name == *name) {
+                Some(v) => v,
+                None => {
+                    errors.push(format!("`{}` is not a member of workspace", name));
+                    continue;
+                }
+            };
+
+            if let Some(published) = published.get(name) {
+                if bump.version <= *published {
+                    errors.push(format!(
+                        "new version of `{}` ({}) is not greater than the published version ({})",
+                        name, bump.version, published
+                    ));
+                }
+            }
+
+            for dependant in &bump.dependants {
+                match packages.iter().find(|p| p.name == *dependant) {
+                    Some(p) if p.dependencies.iter().any(|dep| dep.name == package.name) => {}
+                    Some(_) => errors.push(format!(
+                        "`{}` is listed as a dependant of `{}` but does not depend on it",
+                        dependant, name
+                    )),
+                    None => errors.push(format!(
+                        "dependant `{}` of `{}` is not a member of workspace",
+                        dependant, name
+                    )),
+                }
+
+                if !self.crates.contains_key(dependant) {
+                    errors.push(format!(
+                        "dependant `{}` of `{}` does not have a new version",
+                        dependant, name
+                    ));
+                }
+            }
+        }
+
+        if !errors.is_empty() {
+            bail!("plan does not match the workspace:\n{}", errors.join("\n"));
+        }
+
+        Ok(())
+    }
+}
@@ -1,9 +1,19 @@
-use std::{process::Stdio, time::Duration};
+//! `cargo mono publish`, which publishes crates in the order of dependencies.
+
+use std::{
+    collections::HashSet,
+    fmt,
+    fs::{read, write},
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::{Duration, Instant, SystemTime},
+};
 
 use anyhow::{bail, Context, Result};
-use cargo_metadata::{Package, PackageId};
+use cargo_metadata::{DependencyKind, Package, PackageId};
 use clap::Args;
 use petgraph::{algo::toposort, graphmap::DiGraphMap};
+use sha2::{Digest, Sha256};
 use tokio::{
     io::{AsyncBufReadExt, BufReader},
     process::{Child, Command},
@@ -12,8 +22,15 @@ use tokio::{
 };
 
 use crate::{
-    cargo_workspace::fetch_ws_crates,
-    crates_io::{can_publish, fetch_published_version},
+    cargo_workspace::Workspace,
+    check::check_packages,
+    crates_io::{can_publish, is_publish_enabled, is_stripped_on_publish},
+    filter::{CrateFilter, FilterArgs},
+    local_registry::LocalRegistry,
+    message::{Event, MessageFormat},
+    registry::{prefetch_published_versions, Registry, RegistryArgs},
+    requirements::{find_mismatched_requirements, fix_requirements},
+    summary::{Status, Summary, SummaryEntry},
 };
 
 /// Publishes crates and its dependencies.
@@ -30,24 +47,108 @@ pub struct PublishCommand {
     /// Skip verification.
     #[clap(long)]
     pub no_verify: bool,
+
+    /// Only check if crates can be published, without publishing.
+    #[clap(long, conflicts_with = "no_check")]
+    pub check: bool,
+
+    /// Skip checks which run before publishing.
+    #[clap(long)]
+    pub no_check: bool,
+
+    /// Change requirements on workspace members to their local versions if
+    /// they don't match.
+    #[clap(long)]
+    pub fix_requirements: bool,
+
+    /// Crates to include or exclude.
+    #[clap(flatten)]
+    pub filter: FilterArgs,
+
+    /// Format of messages printed to stdout.
+    #[clap(long, value_enum, default_value_t)]
+    pub message_format: MessageFormat,
+
+    /// Registry to publish to.
+    #[clap(flatten)]
+    pub registry: RegistryArgs,
+
+    /// Write the summary of the release to the file as Markdown.
+    #[clap(long, value_name = "FILE")]
+    pub summary: Option<PathBuf>,
 }
 
 impl PublishCommand {
+    /// Runs the command in the current workspace.
     pub async fn run(&self) -> Result<()> {
-        let ws_packages = fetch_ws_crates().await?;
-        let ws_packages = ws_packages
-            .into_iter()
-            .filter(can_publish)
+        let registry = self.registry.build()?;
+        let registry = &*registry;
+        let local_registry = self.registry.local_registry()?;
+
+        let mut ws = Workspace::load().await?;
+
+        if self.fix_requirements {
+            let mismatches = find_mismatched_requirements(&ws.members);
+            if !mismatches.is_empty() {
+                fix_requirements(&ws.root, &ws.members, &mismatches, self.message_format).await?;
+                // Reload the manifests patched above.
+                ws = Workspace::load().await?;
+            }
+        }
+        let all_packages = &ws.members;
+
+        let filter = self.filter.build(&ws.config)?;
+
+        // Crates with wildcard dependencies are reported by the checks instead of being
+        // skipped silently.
+        let ws_packages = all_packages
+            .iter()
+            .filter(|p| self.registry.allows(p))
+            .filter(|p| {
+                if self.no_check {
+                    can_publish(p)
+                } else {
+                    is_publish_enabled(p)
+                }
+            })
+            .filter(|p| filter.matches(p))
+            .cloned()
             .collect::<Vec<_>>();
 
         let target_crate = &*self.crate_name;
         let allow_only_deps = self.allow_only_deps;
-        let graph = dependency_graph(&ws_packages, target_crate);
+        if target_crate != "*" {
+            match all_packages.iter().find(|p| p.name == target_crate) {
+                None => bail!("`{}` is not a member of the workspace", target_crate),
+                Some(p) if !filter.matches(p) => {
+                    bail!("`{}` is excluded by `--include`/`--exclude`", target_crate)
+                }
+                _ => {}
+            }
+        }
+
+        prefetch_published_versions(
+            registry,
+            all_packages
+                .iter()
+                .filter(|p| is_publish_enabled(p))
+                .map(|p| &*p.name),
+        )
+        .await?;
+
+        let graph = dependency_graph(&ws_packages, target_crate, !allow_only_deps)?;
+
+        // Only crates which will be published need their dependencies.
+        let in_graph = ws_packages
+            .iter()
+            .filter(|p| graph.contains_node(&p.id))
+            .collect::<Vec<_>>();
+        check_excluded_deps(registry, all_packages, &in_graph, &filter).await?;
 
         if !allow_only_deps {
             let p = ws_packages.iter().find(|p| p.name == target_crate);
             if let Some(p) = p {
-                let published_version = fetch_published_version(&p.name, true).await?;
+                let published_version = registry.published_version(&p.name, true).await?;
 
                 if published_version >= p.version {
                     bail!("version of `{}` is same as published version", p.name)
@@ -60,43 +161,343 @@ impl PublishCommand {
             Err(e) => bail!("circular dependency detected: {:?}", e),
         };
 
+        if !self.no_check {
+            let mut to_publish = vec![];
+            for p in &packages {
+                if let Some(pkg) = ws_packages.iter().find(|ws_pkg| ws_pkg.id == **p) {
+                    if registry.published_version(&pkg.name, true).await? < pkg.version {
+                        to_publish.push(pkg);
+                    }
+                }
+            }
+
+            check_packages(all_packages, &to_publish).await?;
+            eprintln!("Checked {} crate(s) to publish", to_publish.len());
+        }
+
+        if self.check {
+            return Ok(());
+        }
+
+        if let Some(local_registry) = &local_registry {
+            local_registry
+                .vendor_dependencies()
+                .await
+                .context("failed to copy dependencies to the local registry")?;
+        }
+
+        let mut summary = Summary::default();
+
+        // Crates which are skipped because they can't be published
+        let relevant = dependency_graph(all_packages, target_crate, true)?;
+        for p in all_packages {
+            if !relevant.contains_node(&p.id) {
+                continue;
+            }
+            if !filter.matches(p) {
+                self.message_format.emit(&Event::Skipped {
+                    package: &p.name,
+                    version: p.version.to_string(),
+                    published_version: None,
+                    reason: "excluded",
+                    detail: None,
+                });
+                continue;
+            }
+            let reason = match self.registry.unpublishable_reason(p) {
+                Some(reason) if !ws_packages.iter().any(|ws_pkg| ws_pkg.id == p.id) => reason,
+                _ => continue,
+            };
+
+            self.message_format.emit(&Event::Skipped {
+                package: &p.name,
+                version: p.version.to_string(),
+                published_version: None,
+                reason: "not-publishable",
+                detail: Some(reason.clone()),
+            });
+
+            summary.entries.push(SummaryEntry {
+                name: p.name.clone(),
+                version: p.version.clone(),
+                published_version: None,
+                status: Status::NotPublishable(reason),
+                duration: None,
+            });
+        }
+
+        let mut result = Ok(());
         for p in packages {
             let pkg = ws_packages.iter().find(|ws_pkg| ws_pkg.id == *p);
 
             if let Some(pkg) = pkg {
-                publish_if_possible(
+                if result.is_err() {
+                    let published_version = registry.published_version(&pkg.name, true).await.ok();
+                    summary.entries.push(SummaryEntry {
+                        name: pkg.name.clone(),
+                        version: pkg.version.clone(),
+                        status: match &published_version {
+                            Some(v) if *v >= pkg.version => Status::UpToDate,
+                            _ => Status::NotAttempted,
+                        },
+                        published_version,
+                        duration: None,
+                    });
+                    continue;
+                }
+
+                let start = Instant::now();
+                let published = publish_if_possible(
                     pkg,
                     PublishOpts {
                         no_verify: self.no_verify,
+                        registry,
+                        registry_name: self.registry.registry.as_deref(),
+                        local_registry: local_registry.as_ref(),
+                        target_dir: &ws.target_dir,
+                        message_format: self.message_format,
                     },
                 )
-                .await
-                .context("failed to publish")?;
+                .await;
+
+                let status = match &published {
+                    Ok(true) => Status::Published,
+                    Ok(false) => Status::UpToDate,
+                    Err(err) => Status::Failed(format!("{:#}", err)),
+                };
+                summary.entries.push(SummaryEntry {
+                    name: pkg.name.clone(),
+                    version: pkg.version.clone(),
+                    published_version: registry.published_version(&pkg.name, true).await.ok(),
+                    duration: match status {
+                        Status::UpToDate => None,
+                        _ => Some(start.elapsed()),
+                    },
+                    status,
+                });
+
+                // Remaining crates are still added to the summary.
+                if let Err(err) = published {
+                    result = Err(err.context("failed to publish"));
+                }
             }
         }
 
-        Ok(())
+        let markdown = summary.to_markdown();
+        eprintln!("\nSummary:\n{}", markdown);
+        if let Some(path) = &self.summary {
+            write(path, &markdown)
+                .with_context(|| format!("failed to write summary to {}", path.display()))?;
+        }
+
+        result
     }
 }
-async fn publish_if_possible(package: &Package, opts: PublishOpts) -> Result<()> {
-    eprintln!("Checking if `{}` should be published", package.name);
 
-    let published_version = fetch_published_version(&package.name, true).await?;
+/// Fails if a crate excluded by `filter` should be published before a crate in
+/// `packages`.
+async fn check_excluded_deps(
+    registry: &dyn Registry,
+    all_packages: &[Package],
+    packages: &[&Package],
+    filter: &CrateFilter,
+) -> Result<()> {
+    let mut problems = vec![];
+
+    for p in packages {
+        if registry.published_version(&p.name, true).await? >= p.version {
+            continue;
+        }
+
+        for dep in &p.dependencies {
+            if dep.kind == DependencyKind::Development {
+                continue;
+            }
+
+            let dep_pkg = match all_packages.iter().find(|ws_pkg| ws_pkg.name == dep.name) {
+                Some(v) if !filter.matches(v) => v,
+                _ => continue,
+            };
+
+            if registry.published_version(&dep_pkg.name, true).await? < dep_pkg.version {
+                problems.push(format!(
+                    "`{}` depends on `{}`, which is excluded but not published",
+                    p.name, dep_pkg.name
+                ));
+            }
+        }
+    }
 
-    if published_version < package.version {
-        publish(package, opts).await.context("failed to publish")?;
+    if !problems.is_empty() {
+        bail!("{}", problems.join("\n"));
     }
 
     Ok(())
 }
 
+/// Returns true if the crate is published.
+async fn publish_if_possible(package: &Package, opts: PublishOpts<'_>) -> Result<bool> {
+    eprintln!("Checking if `{}` should be published", package.name);
+
+    let published_version = opts.registry.published_version(&package.name, true).await?;
+
+    if published_version >= package.version {
+        opts.message_format.emit(&Event::Skipped {
+            package: &package.name,
+            version: package.version.to_string(),
+            published_version: Some(published_version.to_string()),
+            reason: "up-to-date",
+            detail: None,
+        });
+        return Ok(false);
+    }
+
+    match publish(package, opts).await {
+        Ok(()) => {
+            opts.message_format.emit(&Event::Published {
+                package: &package.name,
+                version: package.version.to_string(),
+                published_version: published_version.to_string(),
+            });
+
+            Ok(true)
+        }
+        Err(err) => {
+            opts.message_format.emit(&Event::Failed {
+                package: &package.name,
+                version: package.version.to_string(),
+                reason: format!("{:#}", err),
+            });
+
+            Err(err.context(format!("failed to publish `{}`", package.name)))
+        }
+    }
+}
+
+/// Options of [publish].
 #[derive(Debug, Clone, Copy)]
+pub struct PublishOpts<'a> {
+    /// Pass `--no-verify` to cargo.
+    pub no_verify: bool,
+    /// Registry to check if the crate is already published.
+    pub registry: &'a dyn Registry,
+    /// Pass `--registry` to cargo.
+    pub registry_name: Option<&'a str>,
+    /// Publish to the registry instead of crates.io.
+    pub local_registry: Option<&'a LocalRegistry>,
+    /// Target directory of the workspace, where `cargo package` stores `.crate`
+    /// files.
+    pub target_dir: &'a Path,
+    /// Format of events, which also decides where the output of cargo goes.
+    pub message_format: MessageFormat,
+}
+
+/// Maximum number of attempts to run `cargo publish` for a crate.
+const MAX_ATTEMPTS: u32 = 5;
+
+/// Publishes `p`, retrying on rate limits and network errors.
+///
+/// If the same version is already uploaded with the same checksum, it's
+/// treated as a success.
+pub async fn publish(p: &Package, opts: PublishOpts<'_>) -> Result<()> {
+    if let Some(registry) = opts.local_registry {
+        return registry.publish(p, opts.no_verify, opts.target_dir).await;
+    }
+
+    let mut attempt = 1;
+
+    loop {
+        let failure = match run_cargo_publish(p, opts).await? {
+            None => return Ok(()),
+            Some(v) => v,
+        };
+
+        if failure == PublishFailure::AlreadyUploaded
+            && is_uploaded_with_same_checksum(opts.registry, p, opts.target_dir)
+                .await
+                .context("failed to compare checksum of the uploaded crate")?
+        {
+            eprintln!(
+                "`{}` is already uploaded with the same checksum; continuing",
+                p.name
+            );
+            return Ok(());
+        }
+
+        match failure.retry_delay(attempt) {
+            Some(delay) if attempt < MAX_ATTEMPTS => {
+                eprintln!(
+                    "Publishing `{}` failed: {}. Retrying in {}s",
+                    p.name,
+                    failure,
+                    delay.as_secs()
+                );
+                sleep(delay).await;
+                attempt += 1;
+            }
+            _ if attempt > 1 => {
+                return Err(anyhow::Error::new(failure)
+                    .context(format!("gave up after {} attempts", attempt)))
+            }
+            _ => return Err(failure.into()),
+        }
+    }
+}
+
+/// Checks if the crate in the registry is the same as the one packaged
+/// locally, which happens if a previous run published it but crashed before
+/// the index was updated.
+///
+/// `target_dir` is the target directory of the workspace of `p`, where
+/// `cargo package` stores the `.crate` file.
+pub async fn is_uploaded_with_same_checksum(
+    registry: &dyn Registry,
+    p: &Package,
+    target_dir: &Path,
+) -> Result<bool> {
+    eprintln!("Packaging `{}` to compare checksum", p.name);
+
+    let output = Command::new("cargo")
+        .arg("package")
+        .arg("--no-verify")
+        .arg("--manifest-path")
+        .arg(&p.manifest_path)
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .context("failed to run `cargo package`")?;
+    if !output.status.success() {
+        bail!(
+            "`cargo package` failed:\n{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    let crate_file = target_dir
+        .join("package")
+        .join(format!("{}-{}.crate", p.name, p.version));
+    let content =
+        read(&crate_file).with_context(|| format!("failed to read {}", crate_file.display()))?;
+    let local = hex::encode(Sha256::digest(&content));
+
+    // The index may not be updated yet.
+    for _ in 0..6 {
+        if let Some(cksum) = registry.fetch_checksum(&p.name, &p.version).await? {
+            return Ok(cksum == local);
+        }
 
-struct PublishOpts {
-    no_verify: bool,
+        sleep(Duration::from_secs(10)).await;
+    }
+
+    bail!(
+        "{} {} is not found in the index even though it's uploaded",
+        p.name,
+        p.version
+    )
 }
 
-async fn publish(p: &Package, opts: PublishOpts) -> Result<()> {
+/// Returns [None] if `cargo publish` succeeded.
+async fn run_cargo_publish(p: &Package, opts: PublishOpts<'_>) -> Result<Option<PublishFailure>> {
     sleep(Duration::new(5, 0)).await;
 
     eprintln!("Publishing `{}`", p.name);
@@ -107,6 +508,9 @@ async fn publish(p: &Package, opts: PublishOpts) -> Result<()> {
     if opts.no_verify {
         cmd.arg("--no-verify");
     }
+    if let Some(name) = opts.registry_name {
+        cmd.arg("--registry").arg(name);
+    }
 
     let mut process: Child = cmd
         .arg("--color")
@@ -124,45 +528,347 @@ async fn publish(p: &Package, opts: PublishOpts) -> Result<()> {
 
     // Ensure the child process is spawned in the runtime so it can
     // make progress on its own while we await for any output.
-    spawn(async move {
-        let status = process
+    let status = spawn(async move {
+        process
             .wait()
             .await
-            .expect("child process encountered an error");
-
-        println!("child status was: {}", status);
+            .expect("child process encountered an error")
     });
 
+    let mut output = String::new();
     while let Some(line) = reader.next_line().await? {
-        println!("{}", line);
+        // stdout is reserved for events in JSON mode.
+        if opts.message_format.is_json() {
+            eprintln!("{}", line);
+        } else {
+            println!("{}", line);
+        }
+        output.push_str(&line);
+        output.push('\n');
     }
 
-    Ok(())
+    let status = status.await.expect("failed to wait for cargo publish");
+    if status.success() {
+        return Ok(None);
+    }
+
+    Ok(Some(PublishFailure::from_stderr(&strip_ansi(&output))))
+}
+
+/// Reason of a failed `cargo publish`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PublishFailure {
+    /// The registry responded with `429 Too Many Requests`.
+    RateLimited {
+        /// When to retry, if the registry told it.
+        retry_after: Option<SystemTime>,
+    },
+    /// Transient network error or a server error of the registry.
+    Network,
+    /// The version is already uploaded.
+    AlreadyUploaded,
+    /// Verification of the packaged crate failed.
+    VerificationFailed,
+    /// Any other error, which is not retried.
+    Other,
 }
 
+impl PublishFailure {
+    fn from_stderr(stderr: &str) -> Self {
+        let lower = stderr.to_ascii_lowercase();
+
+        if lower.contains("already uploaded") || lower.contains("already exists on") {
+            return PublishFailure::AlreadyUploaded;
+        }
+
+        // Not just `429`, which also appears in line numbers and versions.
+        if lower.contains("status 429") || lower.contains("too many requests") {
+            let retry_after = stderr
+                .find("try again after ")
+                .map(|idx| &stderr[idx + "try again after ".len()..])
+                .and_then(|rest| rest.find("GMT").map(|end| &rest[..end + "GMT".len()]))
+                .and_then(|date| httpdate::parse_http_date(date).ok());
+
+            return PublishFailure::RateLimited { retry_after };
+        }
+
+        if lower.contains("failed to verify package tarball") {
+            return PublishFailure::VerificationFailed;
+        }
+
+        const NETWORK_ERRORS: &[&str] = &[
+            "spurious network error",
+            "failed to get successful http response",
+            "couldn't resolve host",
+            "could not resolve host",
+            "connection reset",
+            "connection refused",
+            "timed out",
+            "(status 500",
+            "(status 502",
+            "(status 503",
+            "(status 504",
+        ];
+        if NETWORK_ERRORS.iter().any(|e| lower.contains(e)) {
+            return PublishFailure::Network;
+        }
+
+        PublishFailure::Other
+    }
+
+    /// Returns the delay before the next attempt, or [None] if retrying won't
+    /// help.
+    fn retry_delay(&self, attempt: u32) -> Option<Duration> {
+        let backoff = Duration::from_secs(10 * 2u64.pow(attempt - 1));
+
+        match self {
+            PublishFailure::RateLimited {
+                retry_after: Some(at),
+            } => Some(
+                at.duration_since(SystemTime::now())
+                    .unwrap_or_default()
+                    .max(Duration::from_secs(1)),
+            ),
+            PublishFailure::RateLimited { retry_after: None } | PublishFailure::Network => {
+                Some(backoff)
+            }
+            PublishFailure::AlreadyUploaded
+            | PublishFailure::VerificationFailed
+            | PublishFailure::Other => None,
+        }
+    }
+}
+
+impl fmt::Display for PublishFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PublishFailure::RateLimited { .. } => write!(f, "rate limited by the registry"),
+            PublishFailure::Network => write!(f, "network error"),
+            PublishFailure::AlreadyUploaded => write!(f, "the version is already uploaded"),
+            PublishFailure::VerificationFailed => write!(f, "verification failed"),
+            PublishFailure::Other => write!(f, "`cargo publish` failed"),
+        }
+    }
+}
+
+impl std::error::Error for PublishFailure {}
+
+/// Removes ANSI escape sequences, as `cargo publish` runs with `--color
+/// always`.
+fn strip_ansi(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            // Skip until the final byte of the sequence
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+            continue;
+        }
+
+        out.push(c);
+    }
+
+    out
+}
+
+/// Builds a graph whose edges point from a dependency to its dependant, so
+/// the toposort of it is the order of publishing.
+///
 /// `packages` should contain only workspace members.
-fn dependency_graph<'a>(packages: &'a [Package], target: &str) -> DiGraphMap<&'a PackageId, usize> {
+///
+/// If `target` is not `*`, the graph contains only `target` and its transitive
+/// dependencies in the workspace. `target` itself is excluded if
+/// `include_target` is false.
+///
+/// Dev-dependencies without a version are ignored, as cargo strips them on
+/// publish.
+pub fn dependency_graph<'a>(
+    packages: &'a [Package],
+    target: &str,
+    include_target: bool,
+) -> Result<DiGraphMap<&'a PackageId, usize>> {
     let mut graph = DiGraphMap::new();
 
-    for p in packages {
+    let mut queue = if target == "*" {
+        packages.iter().collect::<Vec<_>>()
+    } else {
+        match packages.iter().find(|p| p.name == target) {
+            Some(p) => vec![p],
+            None => bail!("`{}` is not a publishable member of the workspace", target),
+        }
+    };
+    let mut visited = HashSet::new();
+
+    if !include_target && target != "*" {
+        let p = queue.pop().unwrap();
+        visited.insert(&p.id);
+
+        queue.extend(
+            p.dependencies
+                .iter()
+                .filter(|dep| !is_stripped_on_publish(dep))
+                .filter_map(|dep| packages.iter().find(|p| p.name == dep.name)),
+        );
+    }
+
+    while let Some(p) = queue.pop() {
+        if !visited.insert(&p.id) {
+            continue;
+        }
+
         let pkg_node = graph.add_node(&p.id);
 
-        for dep in &p.dependencies {
+        for dep in p
+            .dependencies
+            .iter()
+            .filter(|dep| !is_stripped_on_publish(dep))
+        {
             let dep_pkg = packages.iter().find(|p| p.name == dep.name);
 
             // Local dependency
             if let Some(dep_pkg) = dep_pkg {
+                if !include_target && dep_pkg.name == target {
+                    continue;
+                }
+
                 let dep_node = graph.add_node(&dep_pkg.id);
 
                 graph.add_edge(dep_node, pkg_node, 1);
+                queue.push(dep_pkg);
             }
+        }
+    }
+
+    Ok(graph)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::{strip_ansi, PublishFailure};
+
+    const RATE_LIMITED: &str = "\
+\u{1b}[1m\u{1b}[31merror\u{1b}[0m\u{1b}[1m:\u{1b}[0m failed to publish \
+                                to registry at https://crates.io
+
+Caused by:
+  the remote server responded with an error (status 429 Too Many Requests): You have published too \
+                                many new crates in a short period of time. Please try again after \
+                                Mon, 20 May 2024 12:34:56 GMT or email help@crates.io to have \
+                                your limit increased.
+";
+
+    const ALREADY_UPLOADED: &str = "\
+\u{1b}[1m\u{1b}[31merror\u{1b}[0m\u{1b}[1m:\u{1b}[0m failed to \
+                                    publish to registry at https://crates.io
+
+Caused by:
+  the remote server responded with an error (status 400 Bad Request): crate version \
+                                    `swc_common@0.1.0` is already uploaded
+";
+
+    const ALREADY_EXISTS: &str = "\
+error: crate swc_common@0.1.0 already exists on crates.io index
+";
+
+    const NO_MATCHING_PACKAGE: &str = "\
+\u{1b}[1m\u{1b}[31merror\u{1b}[0m\u{1b}[1m:\u{1b}[0m failed to prepare local package for uploading
+
+Caused by:
+  no matching package named `swc_macros` found
+  location searched: registry `crates-io`
+  required by package `swc_common v0.1.0 (/repo/common)`
+";
+
+    #[test]
+    fn strip_ansi_removes_colors() {
+        assert_eq!(
+            strip_ansi("\u{1b}[1m\u{1b}[31merror\u{1b}[0m\u{1b}[1m:\u{1b}[0m failed"),
+            "error: failed"
+        );
+    }
+
+    #[test]
+    fn rate_limited() {
+        let failure = PublishFailure::from_stderr(&strip_ansi(RATE_LIMITED));
 
-            if p.name == target {
-                break;
+        assert_eq!(
+            failure,
+            PublishFailure::RateLimited {
+                retry_after: Some(
+                    httpdate::parse_http_date("Mon, 20 May 2024 12:34:56 GMT").unwrap()
+                ),
             }
+        );
+        // The time is in the past, so it's retried soon.
+        assert_eq!(failure.retry_delay(1), Some(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn rate_limited_in_future() {
+        let at = SystemTime::now() + Duration::from_secs(600);
+        let failure = PublishFailure::RateLimited {
+            retry_after: Some(at),
+        };
+
+        let delay = failure.retry_delay(1).unwrap();
+        assert!(delay > Duration::from_secs(590) && delay <= Duration::from_secs(600));
+    }
+
+    #[test]
+    fn rate_limited_without_date() {
+        let failure = PublishFailure::from_stderr(
+            "error: failed to publish to registry at https://crates.io\n\nCaused by:\n  the \
+             remote server responded with an error (status 429 Too Many Requests)\n",
+        );
+
+        assert_eq!(failure, PublishFailure::RateLimited { retry_after: None });
+        assert_eq!(failure.retry_delay(1), Some(Duration::from_secs(10)));
+        assert_eq!(failure.retry_delay(3), Some(Duration::from_secs(40)));
+    }
+
+    #[test]
+    fn already_uploaded() {
+        for stderr in &[ALREADY_UPLOADED, ALREADY_EXISTS] {
+            let failure = PublishFailure::from_stderr(&strip_ansi(stderr));
+
+            assert_eq!(failure, PublishFailure::AlreadyUploaded);
+            assert_eq!(failure.retry_delay(1), None);
         }
-        //
     }
 
-    graph
+    #[test]
+    fn network_error() {
+        let failure = PublishFailure::from_stderr(
+            "warning: spurious network error (3 tries remaining): [28] Timeout was reached\n",
+        );
+
+        assert_eq!(failure, PublishFailure::Network);
+        assert_eq!(failure.retry_delay(2), Some(Duration::from_secs(20)));
+    }
+
+    #[test]
+    fn verification_failure_mentioning_429() {
+        let failure = PublishFailure::from_stderr(
+            "error[E0425]: cannot find value `x` in this scope\n   --> src/lib.rs:429:5\n\nerror: \
+             failed to verify package tarball\n",
+        );
+
+        assert_eq!(failure, PublishFailure::VerificationFailed);
+        assert_eq!(failure.retry_delay(1), None);
+    }
+
+    #[test]
+    fn plain_failure() {
+        let failure = PublishFailure::from_stderr(&strip_ansi(NO_MATCHING_PACKAGE));
+
+        assert_eq!(failure, PublishFailure::Other);
+        assert_eq!(failure.retry_delay(1), None);
+    }
 }
@@ -0,0 +1,452 @@
+//! Sources of published versions, abstracted by [Registry].
+
+use std::{
+    collections::HashMap,
+    env,
+    fmt::Debug,
+    fs::read_to_string,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use cargo_metadata::Package;
+use clap::Args;
+use dashmap::DashMap;
+use futures_util::{stream, StreamExt, TryStreamExt};
+use semver::Version;
+use tokio::{process::Command, sync::OnceCell};
+
+use crate::{
+    crates_io::{fetch_index_entries, unpublishable_reason, IndexEntry, CRATES_IO_INDEX_URL},
+    http::config_files,
+    local_registry::LocalRegistry,
+};
+
+/// Options selecting the registry, shared by commands.
+#[derive(Debug, Args)]
+pub struct RegistryArgs {
+    /// Use the alternative registry with the name in cargo config instead of
+    /// crates.io. Only sparse registries are supported.
+    #[clap(long, value_name = "NAME", conflicts_with = "local_registry")]
+    pub registry: Option<String>,
+
+    /// Use a directory-backed registry instead of crates.io, for testing.
+    ///
+    /// The directory is created if it does not exist.
+    #[clap(long, value_name = "DIR")]
+    pub local_registry: Option<PathBuf>,
+
+    /// Use only the cached index instead of accessing the network.
+    #[clap(long)]
+    pub offline: bool,
+}
+
+impl RegistryArgs {
+    /// Creates the registry to query.
+    pub fn build(&self) -> Result<Box<dyn Registry>> {
+        let index = if let Some(registry) = self.local_registry()? {
+            SparseIndex::new(&registry.index_url())
+        } else {
+            match &self.registry {
+                Some(name) => SparseIndex::from_cargo_config(name)?,
+                None => SparseIndex::crates_io(),
+            }
+        };
+
+        Ok(Box::new(index.offline(self.offline)))
+    }
+
+    /// Returns true if `p` can be published to the selected registry, based on
+    /// `package.publish`.
+    pub fn allows(&self, p: &Package) -> bool {
+        let name = self.registry.as_deref().unwrap_or("crates-io");
+
+        match &p.publish {
+            None => true,
+            Some(registries) => registries.iter().any(|r| r == name),
+        }
+    }
+
+    /// Returns the reason why `p` can't be published to the selected
+    /// registry, like [crate::crates_io::unpublishable_reason] but also
+    /// checking [RegistryArgs::allows].
+    pub fn unpublishable_reason(&self, p: &Package) -> Option<String> {
+        unpublishable_reason(p).or_else(|| {
+            (!self.allows(p)).then(|| match &self.registry {
+                Some(name) => format!("not allowed to be published to `{}`", name),
+                None => "not allowed to be published to crates.io".into(),
+            })
+        })
+    }
+
+    /// Opens the registry given by `--local-registry`.
+    pub fn local_registry(&self) -> Result<Option<LocalRegistry>> {
+        self.local_registry
+            .as_deref()
+            .map(LocalRegistry::new)
+            .transpose()
+    }
+}
+
+/// A version of a crate known to a [Registry].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublishedVersion {
+    /// The published version.
+    pub version: Version,
+    /// True if the version is yanked.
+    pub yanked: bool,
+    /// SHA256 checksum of the `.crate` file, if the registry knows it.
+    pub checksum: Option<String>,
+}
+
+/// A source of truth for published versions of crates.
+#[async_trait]
+pub trait Registry: Debug + Send + Sync {
+    /// Fetches all versions of a crate, including yanked ones.
+    ///
+    /// Returns an empty list if the crate is not published. This should not
+    /// be cached, so it can be used to wait for the registry to be updated.
+    async fn fetch_versions(&self, name: &str) -> Result<Vec<PublishedVersion>>;
+
+    /// Fetches the latest version of a crate, or `0.0.0` if the crate is not
+    /// published and `allow_not_found` is true.
+    ///
+    /// Implementations may cache the result.
+    async fn published_version(&self, name: &str, allow_not_found: bool) -> Result<Version> {
+        let latest = self
+            .fetch_versions(name)
+            .await?
+            .into_iter()
+            .map(|v| v.version)
+            .max();
+
+        match latest {
+            Some(v) => Ok(v),
+            None if allow_not_found => Ok(Version::new(0, 0, 0)),
+            None => bail!("`{}` is not published", name),
+        }
+    }
+
+    /// Fetches the checksum of a published version.
+    async fn fetch_checksum(&self, name: &str, version: &Version) -> Result<Option<String>> {
+        Ok(self
+            .fetch_versions(name)
+            .await?
+            .into_iter()
+            .find(|v| v.version == *version)
+            .and_then(|v| v.checksum))
+    }
+
+    /// Returns true if the version is published and yanked.
+    async fn is_yanked(&self, name: &str, version: &Version) -> Result<bool> {
+        Ok(self
+            .fetch_versions(name)
+            .await?
+            .iter()
+            .any(|v| v.version == *version && v.yanked))
+    }
+}
+
+/// Maximum number of concurrent requests made by [prefetch_published_versions].
+const PREFETCH_CONCURRENCY: usize = 16;
+
+/// Fetches the published versions of crates concurrently, so that later calls
+/// to [Registry::published_version] use the cache.
+pub async fn prefetch_published_versions<'a, I>(registry: &dyn Registry, names: I) -> Result<()>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    stream::iter(names)
+        .map(|name| registry.published_version(name, true))
+        .buffer_unordered(PREFETCH_CONCURRENCY)
+        .try_collect::<Vec<_>>()
+        .await?;
+
+    Ok(())
+}
+
+/// A registry using the sparse index protocol, like crates.io.
+#[derive(Debug)]
+pub struct SparseIndex {
+    url: String,
+    offline: bool,
+    latest: DashMap<String, Version>,
+}
+
+impl SparseIndex {
+    /// Uses the index at `url`. The `sparse+` prefix of cargo is allowed, and
+    /// `file://` URLs are read from the disk.
+    pub fn new(url: &str) -> Self {
+        SparseIndex {
+            url: url.trim_start_matches("sparse+").to_string(),
+            offline: false,
+            latest: Default::default(),
+        }
+    }
+
+    /// If `offline` is true, index files are read only from the disk cache.
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Uses the index of crates.io.
+    pub fn crates_io() -> Self {
+        Self::new(CRATES_IO_INDEX_URL)
+    }
+
+    /// Uses the alternative registry named `name` in cargo config, which is
+    /// `registries.{name}.index` or `CARGO_REGISTRIES_{NAME}_INDEX`.
+    ///
+    /// Only sparse registries are supported.
+    pub fn from_cargo_config(name: &str) -> Result<Self> {
+        let var = format!(
+            "CARGO_REGISTRIES_{}_INDEX",
+            name.to_uppercase().replace('-', "_")
+        );
+        let index = env::var(&var).ok().or_else(|| {
+            config_files().into_iter().find_map(|file| {
+                read_to_string(file)
+                    .ok()?
+                    .parse::<toml::Value>()
+                    .ok()?
+                    .get("registries")?
+                    .get(name)?
+                    .get("index")?
+                    .as_str()
+                    .map(|v| v.to_string())
+            })
+        });
+
+        match index {
+            Some(index) if index.starts_with("sparse+") => Ok(Self::new(&index)),
+            Some(index) => bail!(
+                "registry `{}` uses the git index `{}`, but only sparse indexes are supported",
+                name,
+                index
+            ),
+            None => bail!(
+                "registry `{}` is not configured; add `registries.{}.index` to cargo config",
+                name,
+                name
+            ),
+        }
+    }
+
+    /// Fetches the full records of a crate in the index.
+    pub async fn fetch_entries(&self, name: &str) -> Result<Vec<IndexEntry>> {
+        fetch_index_entries(&self.url, name, self.offline).await
+    }
+}
+
+#[async_trait]
+impl Registry for SparseIndex {
+    async fn fetch_versions(&self, name: &str) -> Result<Vec<PublishedVersion>> {
+        Ok(self
+            .fetch_entries(name)
+            .await?
+            .into_iter()
+            .map(|entry| PublishedVersion {
+                version: entry.vers,
+                yanked: entry.yanked,
+                checksum: Some(entry.cksum),
+            })
+            .collect())
+    }
+
+    async fn published_version(&self, name: &str, allow_not_found: bool) -> Result<Version> {
+        if let Some(v) = self.latest.get(name) {
+            return Ok(v.clone());
+        }
+
+        let versions = self.fetch_versions(name).await?;
+        let v = match versions.into_iter().map(|v| v.version).max() {
+            Some(v) => v,
+            None if allow_not_found => Version::new(0, 0, 0),
+            None => bail!("`{}` is not published", name),
+        };
+
+        self.latest.insert(name.to_string(), v.clone());
+        Ok(v)
+    }
+}
+
+/// Versions from git tags like `{name}@{version}`, `{name}@v{version}`,
+/// `{name}-v{version}` or `{name}/v{version}`, for crates which are not
+/// published to a registry.
+#[derive(Debug)]
+pub struct GitTags {
+    repo: PathBuf,
+    tags: OnceCell<Vec<String>>,
+}
+
+impl GitTags {
+    /// Reads tags of the git repository containing `repo`.
+    pub fn new(repo: &Path) -> Self {
+        GitTags {
+            repo: repo.to_path_buf(),
+            tags: OnceCell::new(),
+        }
+    }
+
+    async fn tags(&self) -> Result<&[String]> {
+        let tags = self
+            .tags
+            .get_or_try_init(|| async {
+                let output = Command::new("git")
+                    .arg("tag")
+                    .arg("--list")
+                    .current_dir(&self.repo)
+                    .output()
+                    .await
+                    .context("failed to run `git tag`")?;
+
+                if !output.status.success() {
+                    bail!(
+                        "`git tag` failed:\n{}",
+                        String::from_utf8_lossy(&output.stderr)
+                    );
+                }
+
+                Ok(String::from_utf8_lossy(&output.stdout)
+                    .lines()
+                    .map(|v| v.trim().to_string())
+                    .collect())
+            })
+            .await?;
+
+        Ok(tags)
+    }
+}
+
+/// Parses the version in `tag` if it's a tag of `name`.
+fn parse_tag(name: &str, tag: &str) -> Option<Version> {
+    let rest = tag.strip_prefix(name)?;
+    let version = ["@v", "@", "-v", "/v"]
+        .iter()
+        .find_map(|sep| rest.strip_prefix(sep))?;
+
+    Version::parse(version).ok()
+}
+
+#[async_trait]
+impl Registry for GitTags {
+    async fn fetch_versions(&self, name: &str) -> Result<Vec<PublishedVersion>> {
+        Ok(self
+            .tags()
+            .await?
+            .iter()
+            .filter_map(|tag| parse_tag(name, tag))
+            .map(|version| PublishedVersion {
+                version,
+                yanked: false,
+                checksum: None,
+            })
+            .collect())
+    }
+}
+
+/// Versions in manifests of the workspace, used as if they are published.
+#[derive(Debug, Default)]
+pub struct ManifestVersions {
+    versions: HashMap<String, Version>,
+}
+
+impl ManifestVersions {
+    /// Uses the current versions of `packages`.
+    pub fn new(packages: &[Package]) -> Self {
+        ManifestVersions {
+            versions: packages
+                .iter()
+                .map(|p| (p.name.clone(), p.version.clone()))
+                .collect(),
+        }
+    }
+}
+
+#[async_trait]
+impl Registry for ManifestVersions {
+    async fn fetch_versions(&self, name: &str) -> Result<Vec<PublishedVersion>> {
+        Ok(self
+            .versions
+            .get(name)
+            .map(|version| PublishedVersion {
+                version: version.clone(),
+                yanked: false,
+                checksum: None,
+            })
+            .into_iter()
+            .collect())
+    }
+}
+
+/// Uses `fallback` for crates which are unknown to `primary`.
+#[derive(Debug)]
+pub struct Fallback<P, F> {
+    /// Registry queried first.
+    pub primary: P,
+    /// Registry queried if `primary` does not know the crate.
+    pub fallback: F,
+}
+
+#[async_trait]
+impl<P, F> Registry for Fallback<P, F>
+where
+    P: Registry,
+    F: Registry,
+{
+    async fn fetch_versions(&self, name: &str) -> Result<Vec<PublishedVersion>> {
+        let versions = self.primary.fetch_versions(name).await?;
+        if !versions.is_empty() {
+            return Ok(versions);
+        }
+
+        self.fallback.fetch_versions(name).await
+    }
+}
+
+/// A registry in memory, for tests.
+#[derive(Debug, Default)]
+pub struct FakeRegistry {
+    crates: Mutex<HashMap<String, Vec<PublishedVersion>>>,
+}
+
+impl FakeRegistry {
+    /// Adds a version of a crate.
+    pub fn publish(&self, name: &str, version: Version, checksum: Option<String>) {
+        self.crates
+            .lock()
+            .unwrap()
+            .entry(name.to_string())
+            .or_default()
+            .push(PublishedVersion {
+                version,
+                yanked: false,
+                checksum,
+            });
+    }
+
+    /// Marks a version of a crate as yanked.
+    pub fn yank(&self, name: &str, version: &Version) {
+        if let Some(versions) = self.crates.lock().unwrap().get_mut(name) {
+            for v in versions.iter_mut().filter(|v| v.version == *version) {
+                v.yanked = true;
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Registry for FakeRegistry {
+    async fn fetch_versions(&self, name: &str) -> Result<Vec<PublishedVersion>> {
+        Ok(self
+            .crates
+            .lock()
+            .unwrap()
+            .get(name)
+            .cloned()
+            .unwrap_or_default())
+    }
+}
@@ -0,0 +1,101 @@
+//! Requirements on workspace members, which should match their local versions.
+
+use std::{collections::HashMap, fmt, path::Path, sync::Arc};
+
+use anyhow::{Context, Result};
+use cargo_metadata::Package;
+use semver::{Version, VersionReq};
+
+use crate::{
+    crates_io::has_no_version,
+    manifest::{patch, patch_workspace_deps},
+    message::MessageFormat,
+};
+
+/// A requirement on a workspace member which is not satisfied by the local
+/// version of the member.
+#[derive(Debug, Clone)]
+pub struct MismatchedRequirement {
+    /// Name of the dependant.
+    pub package: String,
+    /// Name of the workspace member.
+    pub dependency: String,
+    /// The requirement in the manifest of the dependant.
+    pub req: VersionReq,
+    /// Version of the workspace member.
+    pub local_version: Version,
+}
+
+impl fmt::Display for MismatchedRequirement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: requirement on `{}` (`{}`) is not satisfied by the local version {}",
+            self.package, self.dependency, self.req, self.local_version
+        )
+    }
+}
+
+/// Finds requirements on workspace members which are not satisfied by the
+/// local versions of the members.
+///
+/// Only path dependencies with a version requirement are considered, as
+/// others are not resolved to the local crate.
+pub fn find_mismatched_requirements(ws_packages: &[Package]) -> Vec<MismatchedRequirement> {
+    let mut mismatches = vec![];
+
+    for p in ws_packages {
+        for dep in &p.dependencies {
+            if dep.path.is_none() || has_no_version(dep) {
+                continue;
+            }
+
+            if let Some(local) = ws_packages.iter().find(|ws_pkg| ws_pkg.name == dep.name) {
+                if !dep.req.matches(&local.version) {
+                    mismatches.push(MismatchedRequirement {
+                        package: p.name.clone(),
+                        dependency: dep.name.clone(),
+                        req: dep.req.clone(),
+                        local_version: local.version.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    mismatches
+}
+
+/// Changes requirements in `mismatches` to the local versions.
+///
+/// `ws_root` is the root of the workspace of `ws_packages`, where
+/// `[workspace.dependencies]` is patched.
+pub async fn fix_requirements(
+    ws_root: &Path,
+    ws_packages: &[Package],
+    mismatches: &[MismatchedRequirement],
+    message_format: MessageFormat,
+) -> Result<()> {
+    let mut deps_by_package = HashMap::<_, HashMap<_, _>>::new();
+    let mut all_deps = HashMap::new();
+    for m in mismatches {
+        all_deps.insert(m.dependency.clone(), m.local_version.clone());
+        deps_by_package
+            .entry(&*m.package)
+            .or_default()
+            .insert(m.dependency.clone(), m.local_version.clone());
+    }
+
+    for p in ws_packages {
+        if let Some(deps) = deps_by_package.remove(&*p.name) {
+            patch(p.clone(), None, Arc::new(deps), message_format)
+                .await
+                .with_context(|| format!("failed to fix requirements of {}", p.name))?;
+        }
+    }
+
+    // Requirements inherited using `{ workspace = true }`
+    patch_workspace_deps(ws_root, Arc::new(all_deps))
+        .await
+        .context("failed to fix requirements in `[workspace.dependencies]`")
+}
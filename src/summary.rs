@@ -0,0 +1,109 @@
+//! Summary of a release printed after `cargo mono publish`.
+
+use std::{fmt::Write, time::Duration};
+
+use semver::Version;
+
+/// Result of `cargo mono publish` for a crate.
+#[derive(Debug, Clone)]
+pub enum Status {
+    /// The crate is published by this run.
+    Published,
+    /// The published version is same as the local version.
+    UpToDate,
+    /// The crate can't be published, with the reason.
+    NotPublishable(String),
+    /// Publishing the crate failed, with the reason.
+    Failed(String),
+    /// The crate is not published because publishing an earlier crate failed.
+    NotAttempted,
+}
+
+/// A row of [Summary].
+#[derive(Debug, Clone)]
+pub struct SummaryEntry {
+    /// Name of the crate.
+    pub name: String,
+    /// The local version.
+    pub version: Version,
+    /// The version published before this run, if known.
+    pub published_version: Option<Version>,
+    /// What happened to the crate.
+    pub status: Status,
+    /// Time spent on publishing the crate.
+    pub duration: Option<Duration>,
+}
+
+/// Summary printed at the end of `cargo mono publish`.
+#[derive(Debug, Default)]
+pub struct Summary {
+    /// Crates in the order they are processed.
+    pub entries: Vec<SummaryEntry>,
+}
+
+impl Summary {
+    /// Formats the summary as a Markdown table, which is also readable in a
+    /// terminal.
+    pub fn to_markdown(&self) -> String {
+        let mut rows = vec![[
+            "Crate".to_string(),
+            "Version".to_string(),
+            "Previously published".to_string(),
+            "Status".to_string(),
+            "Duration".to_string(),
+        ]];
+
+        for e in &self.entries {
+            let status = match &e.status {
+                Status::Published => "published".to_string(),
+                Status::UpToDate => "skipped (up to date)".to_string(),
+                Status::NotPublishable(reason) => format!("skipped ({})", reason),
+                Status::Failed(reason) => format!("failed: {}", reason.replace('\n', " ")),
+                Status::NotAttempted => "not attempted (an earlier crate failed)".to_string(),
+            };
+
+            rows.push([
+                format!("`{}`", e.name),
+                e.version.to_string(),
+                e.published_version
+                    .as_ref()
+                    .map(|v| v.to_string())
+                    .unwrap_or_else(|| "-".into()),
+                status.replace('|', "\\|"),
+                e.duration
+                    .map(|d| format!("{:.1}s", d.as_secs_f64()))
+                    .unwrap_or_else(|| "-".into()),
+            ]);
+        }
+
+        markdown_table(&rows)
+    }
+}
+
+/// Formats `rows` as a Markdown table with padded columns. The first row is
+/// the header.
+pub(crate) fn markdown_table<const N: usize>(rows: &[[String; N]]) -> String {
+    let mut widths = [0; N];
+    for row in rows {
+        for (w, cell) in widths.iter_mut().zip(row) {
+            *w = (*w).max(cell.len());
+        }
+    }
+
+    let mut out = String::new();
+    for (idx, row) in rows.iter().enumerate() {
+        for (w, cell) in widths.iter().zip(row) {
+            let _ = write!(out, "| {:w$} ", cell, w = w);
+        }
+        out.push_str("|\n");
+
+        if idx == 0 {
+            for w in &widths {
+                let _ = write!(out, "|-{}-", "-".repeat(*w));
+            }
+            out.push_str("|\n");
+        }
+    }
+
+    out
+}
@@ -0,0 +1,613 @@
+mod common;
+
+use common::{events, Fixture};
+
+const ROOT: &str = r#"[workspace]
+members = [
+    "mono_base",
+    "mono_string",
+    "mono_inline",
+    "mono_table",
+    "mono_inherited",
+    "mono_target",
+    "mono_renamed",
+    "mono_private",
+]
+
+[workspace.dependencies]
+mono_base = { version = "0.1.0", path = "mono_base" }
+
+[patch.crates-io]
+mono_base = { path = "mono_base" }
+"#;
+
+fn fixture() -> Fixture {
+    let fixture = Fixture::new(&[
+        ("Cargo.toml", ROOT),
+        (
+            "mono_base/Cargo.toml",
+            r#"[package]
+name = "mono_base"
+version = "0.1.0"
+edition = "2018"
+"#,
+        ),
+        (
+            "mono_string/Cargo.toml",
+            r#"[package]
+name = "mono_string"
+version = "0.1.0"
+edition = "2018"
+
+[dependencies]
+mono_base = "0.1.0"
+"#,
+        ),
+        (
+            "mono_inline/Cargo.toml",
+            r#"[package]
+name = "mono_inline"
+version = "0.1.0"
+edition = "2018"
+
+[dependencies]
+mono_base = { version = "0.1.0", path = "../mono_base" }
+
+[dev-dependencies]
+mono_base = { path = "../mono_base" }
+"#,
+        ),
+        (
+            "mono_table/Cargo.toml",
+            r#"[package]
+name = "mono_table"
+version = "0.1.0"
+edition = "2018"
+
+[dev-dependencies.mono_base]
+version = "0.1.0"
+path = "../mono_base"
+"#,
+        ),
+        (
+            "mono_inherited/Cargo.toml",
+            r#"[package]
+name = "mono_inherited"
+version = "0.1.0"
+edition = "2018"
+
+[dependencies]
+mono_base = { workspace = true }
+"#,
+        ),
+        (
+            "mono_target/Cargo.toml",
+            r#"[package]
+name = "mono_target"
+version = "0.1.0"
+edition = "2018"
+
+[target.'cfg(unix)'.dependencies]
+mono_base = { version = "0.1.0", path = "../mono_base" }
+"#,
+        ),
+        (
+            "mono_renamed/Cargo.toml",
+            r#"[package]
+name = "mono_renamed"
+version = "0.1.0"
+edition = "2018"
+
+[build-dependencies]
+base = { package = "mono_base", version = "0.1.0", path = "../mono_base" }
+"#,
+        ),
+        (
+            "mono_private/Cargo.toml",
+            r#"[package]
+name = "mono_private"
+version = "0.1.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+mono_base = { version = "0.1.0", path = "../mono_base" }
+"#,
+        ),
+    ]);
+
+    for name in &[
+        "mono_base",
+        "mono_string",
+        "mono_inline",
+        "mono_table",
+        "mono_inherited",
+        "mono_target",
+        "mono_renamed",
+    ] {
+        fixture.publish_fake(name, "0.1.0");
+    }
+
+    fixture
+}
+
+#[test]
+fn plan_of_breaking_change() {
+    let fixture = fixture();
+
+    fixture.run_ok(&[
+        "bump",
+        "mono_base",
+        "--breaking",
+        "--save-plan",
+        "plan.toml",
+    ]);
+
+    assert_eq!(
+        fixture.read("plan.toml"),
+        r#"[crate.mono_base]
+version = '0.2.0'
+breaking = true
+dependants = [
+    'mono_inherited',
+    'mono_inline',
+    'mono_renamed',
+    'mono_string',
+    'mono_table',
+    'mono_target',
+]
+
+[crate.mono_inherited]
+version = '0.2.0'
+breaking = true
+dependants = []
+
+[crate.mono_inline]
+version = '0.2.0'
+breaking = true
+dependants = []
+
+[crate.mono_renamed]
+version = '0.2.0'
+breaking = true
+dependants = []
+
+[crate.mono_string]
+version = '0.2.0'
+breaking = true
+dependants = []
+
+[crate.mono_table]
+version = '0.2.0'
+breaking = true
+dependants = []
+
+[crate.mono_target]
+version = '0.2.0'
+breaking = true
+dependants = []
+"#
+    );
+}
+
+#[test]
+fn plan_is_based_on_published_version() {
+    let fixture = fixture();
+    fixture.publish_fake("mono_base", "0.3.1");
+
+    fixture.run_ok(&["bump", "mono_base", "--save-plan", "plan.toml"]);
+
+    assert_eq!(
+        fixture.read("plan.toml"),
+        r#"[crate.mono_base]
+version = '0.3.2'
+breaking = false
+dependants = []
+"#
+    );
+}
+
+#[test]
+fn plan_respects_filters() {
+    let fixture = fixture();
+    fixture.run_ok(&["bump", "mono_base", "--save-plan", "plan.toml"]);
+
+    let output = fixture.run(&["bump", "--plan", "plan.toml", "--exclude", "mono_base"]);
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains(
+        "`mono_base` cannot be bumped because of `--include`, `--exclude` or `publish` of the \
+         manifest"
+    ));
+    assert!(fixture
+        .read("mono_base/Cargo.toml")
+        .contains(r#"version = "0.1.0""#));
+}
+
+/// `mono_shared` depends on both `mono_x` and `mono_y`, and all of them are
+/// published.
+fn shared_dependant_fixture() -> Fixture {
+    let manifest = |name: &str, deps: &str| {
+        format!(
+            "[package]\nname = \"{}\"\nversion = \"0.1.0\"\nedition = \"2018\"\n{}",
+            name, deps
+        )
+    };
+
+    let fixture = Fixture::new(&[
+        (
+            "Cargo.toml",
+            r#"[workspace]
+members = ["mono_x", "mono_y", "mono_shared"]
+"#,
+        ),
+        ("mono_x/Cargo.toml", &manifest("mono_x", "")),
+        ("mono_y/Cargo.toml", &manifest("mono_y", "")),
+        (
+            "mono_shared/Cargo.toml",
+            &manifest(
+                "mono_shared",
+                r#"
+[dependencies]
+mono_x = { version = "0.1.0", path = "../mono_x" }
+mono_y = { version = "0.1.0", path = "../mono_y" }
+"#,
+            ),
+        ),
+    ]);
+    for name in &["mono_x", "mono_y", "mono_shared"] {
+        fixture.publish_fake(name, "0.1.0");
+    }
+
+    fixture
+}
+
+const SHARED_DEPENDANT_PLAN: &str = r#"[crate.mono_shared]
+version = '0.1.1'
+breaking = false
+dependants = []
+
+[crate.mono_x]
+version = '0.1.1'
+breaking = false
+dependants = ['mono_shared']
+
+[crate.mono_y]
+version = '0.1.1'
+breaking = false
+dependants = ['mono_shared']
+"#;
+
+#[test]
+fn shared_dependant_is_bumped_once() {
+    let fixture = shared_dependant_fixture();
+
+    fixture.run_ok(&["bump", "mono_x", "mono_y", "-D", "--save-plan", "plan.toml"]);
+
+    assert_eq!(fixture.read("plan.toml"), SHARED_DEPENDANT_PLAN);
+}
+
+#[test]
+fn crates_matching_glob_are_bumped() {
+    let fixture = shared_dependant_fixture();
+
+    // `mono_shared` is matched by the pattern and is also a dependant.
+    fixture.run_ok(&["bump", "mono_*", "-D", "--save-plan", "plan.toml"]);
+
+    assert_eq!(fixture.read("plan.toml"), SHARED_DEPENDANT_PLAN);
+}
+
+#[test]
+fn patch_breaking_change() {
+    let fixture = fixture();
+
+    fixture.run_ok(&["bump", "mono_base", "--breaking", "--fix-requirements"]);
+
+    assert_eq!(
+        fixture.read("Cargo.toml"),
+        ROOT.replace(
+            r#"mono_base = { version = "0.1.0", path = "mono_base" }"#,
+            r#"mono_base = { version = "0.2.0", path = "mono_base" }"#
+        )
+    );
+    assert_eq!(
+        fixture.read("mono_base/Cargo.toml"),
+        r#"[package]
+name = "mono_base"
+version = "0.2.0"
+edition = "2018"
+"#
+    );
+    assert_eq!(
+        fixture.read("mono_string/Cargo.toml"),
+        r#"[package]
+name = "mono_string"
+version = "0.2.0"
+edition = "2018"
+
+[dependencies]
+mono_base = "0.2.0"
+"#
+    );
+    assert_eq!(
+        fixture.read("mono_inline/Cargo.toml"),
+        r#"[package]
+name = "mono_inline"
+version = "0.2.0"
+edition = "2018"
+
+[dependencies]
+mono_base = { version = "0.2.0", path = "../mono_base" }
+
+[dev-dependencies]
+mono_base = { path = "../mono_base" }
+"#
+    );
+    assert_eq!(
+        fixture.read("mono_table/Cargo.toml"),
+        r#"[package]
+name = "mono_table"
+version = "0.2.0"
+edition = "2018"
+
+[dev-dependencies.mono_base]
+version = "0.2.0"
+path = "../mono_base"
+"#
+    );
+    assert_eq!(
+        fixture.read("mono_inherited/Cargo.toml"),
+        r#"[package]
+name = "mono_inherited"
+version = "0.2.0"
+edition = "2018"
+
+[dependencies]
+mono_base = { workspace = true }
+"#
+    );
+    assert_eq!(
+        fixture.read("mono_target/Cargo.toml"),
+        r#"[package]
+name = "mono_target"
+version = "0.2.0"
+edition = "2018"
+
+[target.'cfg(unix)'.dependencies]
+mono_base = { version = "0.2.0", path = "../mono_base" }
+"#
+    );
+    assert_eq!(
+        fixture.read("mono_renamed/Cargo.toml"),
+        r#"[package]
+name = "mono_renamed"
+version = "0.2.0"
+edition = "2018"
+
+[build-dependencies]
+base = { package = "mono_base", version = "0.2.0", path = "../mono_base" }
+"#
+    );
+    // Not bumped, as it's not published, but the requirement is fixed.
+    assert_eq!(
+        fixture.read("mono_private/Cargo.toml"),
+        r#"[package]
+name = "mono_private"
+version = "0.1.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+mono_base = { version = "0.2.0", path = "../mono_base" }
+"#
+    );
+}
+
+#[test]
+fn patch_compatible_change() {
+    let fixture = fixture();
+
+    fixture.run_ok(&["bump", "mono_base"]);
+
+    assert_eq!(
+        fixture.read("mono_base/Cargo.toml"),
+        r#"[package]
+name = "mono_base"
+version = "0.1.1"
+edition = "2018"
+"#
+    );
+    // `0.1.0` is satisfied by `0.1.1`, so dependants are not modified, while the
+    // shared requirement follows the new version.
+    assert_eq!(
+        fixture.read("Cargo.toml"),
+        ROOT.replace(
+            r#"mono_base = { version = "0.1.0", path = "mono_base" }"#,
+            r#"mono_base = { version = "0.1.1", path = "mono_base" }"#
+        )
+    );
+    assert!(fixture
+        .read("mono_inline/Cargo.toml")
+        .contains(r#"version = "0.1.0""#));
+}
+
+#[test]
+fn mismatched_requirement_is_rolled_back() {
+    let fixture = fixture();
+
+    let output = fixture.run(&[
+        "bump",
+        "mono_base",
+        "--breaking",
+        "--message-format",
+        "json",
+    ]);
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains(
+        "mono_private: requirement on `mono_base` (`>=0.1.0, <0.2.0`) is not satisfied by the \
+         local version 0.2.0"
+    ));
+
+    let events = events(&output);
+    assert!(events.iter().any(|e| e["event"] == "manifest-patched"));
+    let last = events.last().unwrap();
+    assert_eq!(last["event"], "rolled-back");
+    assert!(last["files"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|file| file.as_str().unwrap().ends_with("mono_base/Cargo.toml")));
+    assert_eq!(fixture.read("Cargo.toml"), ROOT);
+    assert!(fixture
+        .read("mono_base/Cargo.toml")
+        .contains(r#"version = "0.1.0""#));
+}
+
+#[test]
+fn prompts_require_terminal() {
+    let fixture = fixture();
+
+    // stdin of the binary is not a terminal in tests.
+    let stderr = |args: &[&str]| {
+        let output = fixture.run(args);
+        assert!(!output.status.success());
+        String::from_utf8_lossy(&output.stderr).into_owned()
+    };
+
+    let without_crate = stderr(&["bump"]);
+    assert!(without_crate.contains("cannot prompt because stdin is not a terminal."));
+    assert!(without_crate.contains(
+        "Pass the name of the crate to bump (`cargo mono bump <crate>`) or apply a saved plan \
+         using `--plan <FILE>`."
+    ));
+
+    let interactive = stderr(&["bump", "-i", "mono_base"]);
+    assert!(interactive.contains("cannot prompt because stdin is not a terminal."));
+    assert!(interactive.contains(
+        "`--interactive` requires a terminal. Use `--breaking` or `--with-dependants` instead, or \
+         apply a saved plan using `--plan <FILE>`."
+    ));
+
+    assert!(fixture
+        .read("mono_base/Cargo.toml")
+        .contains(r#"version = "0.1.0""#));
+}
+
+#[test]
+fn private_crate_cannot_be_bumped() {
+    let fixture = fixture();
+
+    let output = fixture.run(&["bump", "mono_private"]);
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("no crate in the workspace matches `mono_private`"));
+}
+
+#[test]
+fn baseline_from_manifest() {
+    let fixture = fixture();
+
+    fixture.run_ok(&[
+        "bump",
+        "mono_private",
+        "--baseline",
+        "manifest",
+        "--save-plan",
+        "plan.toml",
+    ]);
+
+    assert_eq!(
+        fixture.read("plan.toml"),
+        r#"[crate.mono_private]
+version = '0.1.1'
+breaking = false
+dependants = []
+"#
+    );
+}
+
+#[test]
+fn baseline_from_git_tags() {
+    let fixture = fixture();
+    fixture.git(&["init", "-q"]);
+    fixture.git(&["add", "."]);
+    fixture.git(&["commit", "-q", "-m", "init"]);
+    fixture.git(&["tag", "mono_private@0.4.0"]);
+    fixture.git(&["tag", "mono_private@0.3.0"]);
+
+    fixture.run_ok(&[
+        "bump",
+        "mono_private",
+        "mono_string",
+        "--baseline",
+        "git-tags",
+    ]);
+
+    assert!(fixture
+        .read("mono_private/Cargo.toml")
+        .contains(r#"version = "0.4.1""#));
+    // Not tagged yet, so the version in the manifest is used.
+    assert!(fixture
+        .read("mono_string/Cargo.toml")
+        .contains(r#"version = "0.1.1""#));
+}
+
+#[test]
+fn git_commit_keeps_json_output() {
+    let fixture = fixture();
+    fixture.git(&["init", "-q"]);
+    fixture.git(&["config", "user.name", "test"]);
+    fixture.git(&["config", "user.email", "test@example.com"]);
+    fixture.git(&["add", "."]);
+    fixture.git(&["commit", "-q", "-m", "init"]);
+
+    let output = fixture.run_ok(&["bump", "mono_base", "-g", "--message-format", "json"]);
+
+    // Every line of stdout is an event.
+    assert!(!events(&output).is_empty());
+    let log = std::process::Command::new("git")
+        .args(["log", "-1", "--format=%s"])
+        .current_dir(fixture.ws())
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&log.stdout), "Bump version\n");
+}
+
+#[test]
+fn failed_git_commit_is_an_error() {
+    let fixture = fixture();
+
+    let output = fixture.run(&["bump", "mono_base", "-g"]);
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("failed to commit using git"));
+}
+
+#[test]
+fn local_version_ahead() {
+    let fixture = fixture();
+    fixture.run_ok(&["bump", "mono_base"]);
+
+    let plan = |mode: &str| {
+        fixture.run_ok(&[
+            "bump",
+            "mono_base",
+            "--local-version",
+            mode,
+            "--save-plan",
+            "plan.toml",
+        ]);
+        fixture.read("plan.toml")
+    };
+
+    assert!(plan("ignore").contains("version = '0.1.1'"));
+    assert!(plan("skip").contains("version = '0.1.1'"));
+    assert!(plan("max").contains("version = '0.1.2'"));
+
+    let output = fixture.run_ok(&["bump", "mono_base", "--local-version", "skip"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains(
+        "mono_base: published 0.1.0, local 0.1.1 -> 0.1.1 (already bumped locally, keeping it)"
+    ));
+}
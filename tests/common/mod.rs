@@ -0,0 +1,148 @@
+//! Helpers for integration tests.
+//!
+//! Each test creates a workspace in a temporary directory and runs the
+//! `cargo-mono` binary in it. Versions published to crates.io are faked using
+//! `--local-registry`, so the network is only needed to resolve dependencies
+//! from crates.io which are not cached yet.
+
+#![allow(dead_code)] // Not all helpers are used by every test binary.
+
+use std::{
+    fs::{create_dir_all, read_to_string, write, OpenOptions},
+    io::Write,
+    path::PathBuf,
+    process::{Command, Output},
+};
+
+use tempfile::TempDir;
+
+pub struct Fixture {
+    dir: TempDir,
+}
+
+impl Fixture {
+    /// Creates a workspace with `files`, which are `(path, content)` pairs.
+    pub fn new(files: &[(&str, &str)]) -> Self {
+        let dir = tempfile::tempdir().expect("failed to create a temporary directory");
+        let fixture = Fixture { dir };
+
+        for (path, content) in files {
+            let path = fixture.ws().join(path);
+            create_dir_all(path.parent().unwrap()).unwrap();
+            write(&path, content).unwrap();
+        }
+
+        for (path, _) in files {
+            if path.ends_with("Cargo.toml") && *path != "Cargo.toml" {
+                let src = fixture.ws().join(path).parent().unwrap().join("src");
+                create_dir_all(&src).unwrap();
+                write(src.join("lib.rs"), "").unwrap();
+            }
+        }
+
+        create_dir_all(fixture.registry().join("index")).unwrap();
+
+        fixture
+    }
+
+    /// Root of the workspace.
+    pub fn ws(&self) -> PathBuf {
+        self.dir.path().join("ws")
+    }
+
+    /// Directory passed to `--local-registry`.
+    pub fn registry(&self) -> PathBuf {
+        self.dir.path().join("registry")
+    }
+
+    pub fn read(&self, path: &str) -> String {
+        read_to_string(self.ws().join(path)).unwrap()
+    }
+
+    /// Makes `name@version` look published.
+    pub fn publish_fake(&self, name: &str, version: &str) {
+        let path = self.registry().join("index").join(index_path(name));
+        create_dir_all(path.parent().unwrap()).unwrap();
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .unwrap();
+        writeln!(
+            file,
+            r#"{{"name":"{}","vers":"{}","deps":[],"cksum":"","features":{{}},"yanked":false}}"#,
+            name, version
+        )
+        .unwrap();
+    }
+
+    /// Runs git in the workspace, panicking on failure.
+    pub fn git(&self, args: &[&str]) {
+        let status = Command::new("git")
+            .args(args)
+            .current_dir(self.ws())
+            .env("GIT_AUTHOR_NAME", "test")
+            .env("GIT_AUTHOR_EMAIL", "test@example.com")
+            .env("GIT_COMMITTER_NAME", "test")
+            .env("GIT_COMMITTER_EMAIL", "test@example.com")
+            .status()
+            .expect("failed to run git");
+        assert!(status.success(), "`git {:?}` failed", args);
+    }
+
+    /// Runs `cargo mono <args>` in the workspace.
+    ///
+    /// `--local-registry <registry>` is added for commands which query the
+    /// registry.
+    pub fn run(&self, args: &[&str]) -> Output {
+        // `CARGO` is set by `cargo test`, so the binary expects to be invoked
+        // as `cargo mono`.
+        let mut cmd = Command::new(env!("CARGO_BIN_EXE_cargo-mono"));
+        cmd.arg("mono").args(args);
+        if matches!(
+            args.first(),
+            Some(&"bump") | Some(&"publish") | Some(&"list")
+        ) {
+            cmd.arg("--local-registry").arg(self.registry());
+        }
+
+        let output = cmd
+            .current_dir(self.ws())
+            .env("RUST_BACKTRACE", "0")
+            .output()
+            .expect("failed to run cargo-mono");
+
+        eprintln!("{}", String::from_utf8_lossy(&output.stderr));
+
+        output
+    }
+
+    /// Same as [Fixture::run], but panics if the command fails.
+    pub fn run_ok(&self, args: &[&str]) -> Output {
+        let output = self.run(args);
+        assert!(output.status.success(), "`cargo mono {:?}` failed", args);
+
+        output
+    }
+}
+
+fn index_path(name: &str) -> String {
+    let name = name.to_lowercase();
+
+    match name.len() {
+        1 => format!("1/{}", name),
+        2 => format!("2/{}", name),
+        3 => format!("3/{}/{}", &name[..1], name),
+        _ => format!("{}/{}/{}", &name[..2], &name[2..4], name),
+    }
+}
+
+/// Parses `--message-format json` output.
+pub fn events(output: &Output) -> Vec<serde_json::Value> {
+    String::from_utf8(output.stdout.clone())
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
@@ -0,0 +1,210 @@
+mod common;
+
+use common::Fixture;
+
+fn fixture() -> Fixture {
+    Fixture::new(&[
+        (
+            "Cargo.toml",
+            r#"[workspace]
+members = ["mono_a", "mono_b", "mono_c", "mono_private"]
+"#,
+        ),
+        (
+            "mono_a/Cargo.toml",
+            r#"[package]
+name = "mono_a"
+version = "0.1.0"
+edition = "2018"
+"#,
+        ),
+        (
+            "mono_b/Cargo.toml",
+            r#"[package]
+name = "mono_b"
+version = "0.2.0"
+edition = "2018"
+
+[dependencies]
+mono_a = { version = "0.1.0", path = "../mono_a" }
+
+[target.'cfg(unix)'.dependencies]
+mono_a = { version = "0.1.0", path = "../mono_a" }
+"#,
+        ),
+        (
+            "mono_c/Cargo.toml",
+            r#"[package]
+name = "mono_c"
+version = "0.3.0"
+edition = "2018"
+
+[build-dependencies]
+mono_a = { version = "0.1.0", path = "../mono_a" }
+
+[dev-dependencies]
+mono_b = { version = "0.2.0", path = "../mono_b" }
+"#,
+        ),
+        (
+            "mono_private/Cargo.toml",
+            r#"[package]
+name = "mono_private"
+version = "0.1.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+mono_b = { version = "0.2.0", path = "../mono_b" }
+"#,
+        ),
+    ])
+}
+
+fn graph(fixture: &Fixture, args: &[&str]) -> String {
+    let mut args = args.to_vec();
+    args.insert(0, "graph");
+
+    let output = fixture.run_ok(&args);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn dot() {
+    let fixture = fixture();
+
+    assert_eq!(
+        graph(&fixture, &[]),
+        r#"digraph {
+    "mono_a" [label="mono_a"];
+    "mono_b" [label="mono_b"];
+    "mono_c" [label="mono_c"];
+    "mono_private" [label="mono_private"];
+    "mono_b" -> "mono_a";
+    "mono_private" -> "mono_b";
+}
+"#
+    );
+}
+
+#[test]
+fn dot_with_all_options() {
+    let fixture = fixture();
+
+    assert_eq!(
+        graph(
+            &fixture,
+            &[
+                "--dev",
+                "--build",
+                "--versions",
+                "--publishability",
+                "--dependants-of",
+                "mono_b",
+            ]
+        ),
+        r#"digraph {
+    "mono_a" [label="mono_a 0.1.0"];
+    "mono_b" [label="mono_b 0.2.0", fillcolor=yellow, style="filled"];
+    "mono_c" [label="mono_c 0.3.0", fillcolor=yellow, style="filled"];
+    "mono_private" [label="mono_private 0.1.0", fillcolor=yellow, style="dashed,filled"];
+    "mono_b" -> "mono_a";
+    "mono_c" -> "mono_a" [style=dashed, label="build"];
+    "mono_c" -> "mono_b" [style=dashed, label="dev"];
+    "mono_private" -> "mono_b";
+}
+"#
+    );
+}
+
+#[test]
+fn mermaid() {
+    let fixture = fixture();
+
+    assert_eq!(
+        graph(
+            &fixture,
+            &[
+                "--format",
+                "mermaid",
+                "--dev",
+                "--publishability",
+                "--dependencies-of",
+                "mono_private",
+            ]
+        ),
+        r#"flowchart TD
+    n0["mono_a"]
+    n1["mono_b"]
+    n2["mono_c"]
+    n3["mono_private"]
+    n1 --> n0
+    n2 -.->|dev| n1
+    n3 --> n1
+    classDef unpublishable stroke-dasharray: 5 5
+    class n3 unpublishable
+    classDef highlighted fill:#ff0
+    class n0,n1,n3 highlighted
+"#
+    );
+}
+
+#[test]
+fn json() {
+    let fixture = fixture();
+
+    let graph: serde_json::Value =
+        serde_json::from_str(&graph(&fixture, &["--format", "json"])).unwrap();
+
+    assert_eq!(
+        graph["nodes"][3],
+        serde_json::json!({
+            "name": "mono_private",
+            "version": "0.1.0",
+            "publishable": false,
+            "reason": "`publish = false`",
+            "highlighted": false,
+        })
+    );
+    assert_eq!(
+        graph["edges"],
+        serde_json::json!([
+            { "from": "mono_b", "to": "mono_a", "kind": "normal" },
+            { "from": "mono_c", "to": "mono_a", "kind": "build" },
+            { "from": "mono_c", "to": "mono_b", "kind": "dev" },
+            { "from": "mono_private", "to": "mono_b", "kind": "normal" },
+        ])
+    );
+}
+
+#[test]
+fn json_highlights_only_selected_edges() {
+    let fixture = fixture();
+
+    let graph: serde_json::Value = serde_json::from_str(&graph(
+        &fixture,
+        &["--format", "json", "--dependants-of", "mono_a"],
+    ))
+    .unwrap();
+
+    let highlighted = graph["nodes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|node| node["highlighted"] == true)
+        .map(|node| node["name"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    // `mono_c` depends on `mono_a` only as a build-dependency.
+    assert_eq!(highlighted, ["mono_a", "mono_b", "mono_private"]);
+}
+
+#[test]
+fn unknown_crate() {
+    let fixture = fixture();
+
+    let output = fixture.run(&["graph", "--dependants-of", "mono_d"]);
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("`mono_d` is not a member of the workspace"));
+}
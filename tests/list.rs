@@ -0,0 +1,142 @@
+mod common;
+
+use common::Fixture;
+
+fn fixture() -> Fixture {
+    let fixture = Fixture::new(&[
+        (
+            "Cargo.toml",
+            r#"[workspace]
+members = ["crates/*"]
+"#,
+        ),
+        (
+            "crates/mono_a/Cargo.toml",
+            r#"[package]
+name = "mono_a"
+version = "0.1.0"
+edition = "2018"
+
+[dependencies]
+mono_d = { version = "0.1.0", path = "../mono_d" }
+"#,
+        ),
+        (
+            "crates/mono_b/Cargo.toml",
+            r#"[package]
+name = "mono_b"
+version = "0.2.0"
+edition = "2018"
+
+[dependencies]
+mono_a = { version = "0.1.0", path = "../mono_a" }
+"#,
+        ),
+        (
+            "crates/mono_c/Cargo.toml",
+            r#"[package]
+name = "mono_c"
+version = "0.1.0"
+edition = "2018"
+"#,
+        ),
+        (
+            "crates/mono_d/Cargo.toml",
+            r#"[package]
+name = "mono_d"
+version = "0.1.0"
+edition = "2018"
+
+# Dev-dependencies may form a cycle.
+[dev-dependencies]
+mono_b = { version = "0.2.0", path = "../mono_b" }
+"#,
+        ),
+        (
+            "crates/mono_private/Cargo.toml",
+            r#"[package]
+name = "mono_private"
+version = "0.1.0"
+edition = "2018"
+publish = false
+"#,
+        ),
+    ]);
+
+    fixture.publish_fake("mono_a", "0.1.0");
+    fixture.publish_fake("mono_b", "0.1.0");
+    fixture.publish_fake("mono_c", "0.1.0");
+    fixture.publish_fake("mono_c", "0.2.0");
+
+    fixture
+}
+
+fn list(fixture: &Fixture, args: &[&str]) -> String {
+    let mut args = args.to_vec();
+    args.insert(0, "list");
+
+    let output = fixture.run_ok(&args);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn table() {
+    let fixture = fixture();
+
+    assert_eq!(
+        list(&fixture, &[]),
+        "\
+| Crate        | Path                | Version | Registry | Status      |
+|--------------|---------------------|---------|----------|-------------|
+| mono_a       | crates/mono_a       | 0.1.0   | 0.1.0    | equal       |
+| mono_b       | crates/mono_b       | 0.2.0   | 0.1.0    | ahead       |
+| mono_c       | crates/mono_c       | 0.1.0   | 0.2.0    | behind      |
+| mono_d       | crates/mono_d       | 0.1.0   | -        | unpublished |
+| mono_private | crates/mono_private | 0.1.0   | -        | private     |
+"
+    );
+}
+
+#[test]
+fn topological_json() {
+    let fixture = fixture();
+
+    let entries: serde_json::Value = serde_json::from_str(&list(
+        &fixture,
+        &["--sort", "topological", "--format", "json"],
+    ))
+    .unwrap();
+
+    let names = entries
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["name"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    let pos = |name| names.iter().position(|n| *n == name).unwrap();
+    assert_eq!(names.len(), 5);
+    assert!(pos("mono_d") < pos("mono_a"));
+    assert!(pos("mono_a") < pos("mono_b"));
+
+    assert_eq!(
+        entries[pos("mono_b")],
+        serde_json::json!({
+            "name": "mono_b",
+            "path": "crates/mono_b",
+            "version": "0.2.0",
+            "registry_version": "0.1.0",
+            "status": "ahead",
+        })
+    );
+    assert_eq!(
+        entries[pos("mono_private")],
+        serde_json::json!({
+            "name": "mono_private",
+            "path": "crates/mono_private",
+            "version": "0.1.0",
+            "registry_version": null,
+            "status": "private",
+            "reason": "`publish = false`",
+        })
+    );
+}
@@ -0,0 +1,369 @@
+mod common;
+
+use cargo_metadata::{MetadataCommand, Package};
+use cargo_mono::{
+    local_registry::LocalRegistry, publish::is_uploaded_with_same_checksum, registry::SparseIndex,
+};
+use common::{events, Fixture};
+
+fn fixture() -> Fixture {
+    Fixture::new(&[
+        (
+            "Cargo.toml",
+            r#"[workspace]
+members = ["mono_a", "mono_b", "mono_c", "mono_d", "mono_private"]
+"#,
+        ),
+        (
+            "mono_a/Cargo.toml",
+            r#"[package]
+name = "mono_a"
+version = "0.1.0"
+edition = "2018"
+description = "Fixture"
+license = "MIT"
+repository = "https://example.com"
+
+[dev-dependencies]
+mono_d = { path = "../mono_d" }
+"#,
+        ),
+        (
+            "mono_b/Cargo.toml",
+            r#"[package]
+name = "mono_b"
+version = "0.1.0"
+edition = "2018"
+description = "Fixture"
+license = "MIT"
+repository = "https://example.com"
+
+[target.'cfg(unix)'.dependencies]
+mono_a = { version = "0.1.0", path = "../mono_a" }
+"#,
+        ),
+        (
+            "mono_c/Cargo.toml",
+            r#"[package]
+name = "mono_c"
+version = "0.1.0"
+edition = "2018"
+description = "Fixture"
+license = "MIT"
+repository = "https://example.com"
+
+[dependencies]
+b = { package = "mono_b", version = "0.1.0", path = "../mono_b" }
+"#,
+        ),
+        (
+            "mono_d/Cargo.toml",
+            r#"[package]
+name = "mono_d"
+version = "0.1.0"
+edition = "2018"
+description = "Fixture"
+license = "MIT"
+repository = "https://example.com"
+
+[dependencies.mono_c]
+version = "0.1.0"
+path = "../mono_c"
+
+[dev-dependencies]
+mono_private = { path = "../mono_private" }
+"#,
+        ),
+        (
+            "mono_private/Cargo.toml",
+            r#"[package]
+name = "mono_private"
+version = "0.1.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+mono_a = { version = "0.1.0", path = "../mono_a" }
+"#,
+        ),
+    ])
+}
+
+/// Names of published crates, in order.
+fn published(args: &[&str], fixture: &Fixture) -> Vec<String> {
+    let mut args = args.to_vec();
+    args.extend(&["--no-verify", "--message-format", "json"]);
+
+    events(&fixture.run_ok(&args))
+        .into_iter()
+        .filter(|e| e["event"] == "published")
+        .map(|e| e["package"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn publish_in_dependency_order() {
+    let fixture = fixture();
+
+    assert_eq!(
+        published(&["publish"], &fixture),
+        ["mono_a", "mono_b", "mono_c", "mono_d"]
+    );
+    assert!(fixture.registry().join("mono_d-0.1.0.crate").exists());
+    assert!(!fixture.registry().join("mono_private-0.1.0.crate").exists());
+}
+
+#[test]
+fn publish_dependencies_of_target() {
+    let fixture = fixture();
+
+    assert_eq!(
+        published(&["publish", "mono_c"], &fixture),
+        ["mono_a", "mono_b", "mono_c"]
+    );
+}
+
+#[test]
+fn publish_only_dependencies() {
+    let fixture = fixture();
+
+    assert_eq!(
+        published(&["publish", "mono_c", "--allow-only-deps"], &fixture),
+        ["mono_a", "mono_b"]
+    );
+}
+
+#[test]
+fn skip_published_crates() {
+    let fixture = fixture();
+    fixture.publish_fake("mono_a", "0.1.0");
+    fixture.publish_fake("mono_b", "0.1.0");
+
+    assert_eq!(
+        published(&["publish", "--no-check"], &fixture),
+        ["mono_c", "mono_d"]
+    );
+}
+
+#[test]
+fn excluded_crate_outside_of_target_is_ignored() {
+    let fixture = fixture();
+
+    // `mono_c` depends on `mono_b`, but it's not a dependency of `mono_a`.
+    assert_eq!(
+        published(&["publish", "mono_a", "--exclude", "mono_b"], &fixture),
+        ["mono_a"]
+    );
+}
+
+#[test]
+fn excluded_dependency_of_target_is_an_error() {
+    let fixture = fixture();
+
+    let output = fixture.run(&["publish", "mono_c", "--exclude", "mono_b"]);
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("`mono_c` depends on `mono_b`, which is excluded but not published"));
+}
+
+#[test]
+fn summary_lists_crates_after_failure() {
+    let fixture = fixture();
+    std::fs::write(fixture.ws().join("mono_b/src/lib.rs"), "fn broken(").unwrap();
+
+    let output = fixture.run(&["publish", "--no-check", "--summary", "summary.md"]);
+
+    assert!(!output.status.success());
+    let summary = fixture.read("summary.md");
+    let status = |name: &str| {
+        summary
+            .lines()
+            .find(|line| line.contains(&format!("`{}`", name)))
+            .unwrap()
+            .to_string()
+    };
+    assert!(status("mono_a").contains("| published "));
+    assert!(status("mono_b").contains("| failed: "));
+    assert!(status("mono_c").contains("| not attempted (an earlier crate failed) "));
+    assert!(status("mono_d").contains("| not attempted (an earlier crate failed) "));
+}
+
+#[test]
+fn private_crate_cannot_be_published() {
+    let fixture = fixture();
+
+    let output = fixture.run(&["publish", "mono_private"]);
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("`mono_private` is not a publishable member of the workspace"));
+}
+
+#[test]
+fn publish_with_external_dependency() {
+    let fixture = Fixture::new(&[
+        (
+            "Cargo.toml",
+            r#"[workspace]
+members = ["mono_ext"]
+"#,
+        ),
+        (
+            "mono_ext/Cargo.toml",
+            r#"[package]
+name = "mono_ext"
+version = "0.1.0"
+edition = "2018"
+description = "Fixture"
+license = "MIT"
+repository = "https://example.com"
+
+[dependencies]
+anyhow = "1"
+"#,
+        ),
+    ]);
+
+    assert_eq!(published(&["publish"], &fixture), ["mono_ext"]);
+
+    let index = std::fs::read_to_string(fixture.registry().join("index/an/yh/anyhow")).unwrap();
+    assert_eq!(index.lines().count(), 1);
+}
+
+#[test]
+fn skipped_crates_are_reported() {
+    let fixture = fixture();
+
+    let output = fixture.run_ok(&[
+        "publish",
+        "--exclude",
+        "mono_d",
+        "--no-verify",
+        "--message-format",
+        "json",
+    ]);
+
+    let skipped = events(&output)
+        .into_iter()
+        .filter(|e| e["event"] == "skipped")
+        .collect::<Vec<_>>();
+    assert_eq!(
+        skipped,
+        [
+            serde_json::json!({
+                "event": "skipped",
+                "package": "mono_d",
+                "version": "0.1.0",
+                "published_version": null,
+                "reason": "excluded",
+            }),
+            serde_json::json!({
+                "event": "skipped",
+                "package": "mono_private",
+                "version": "0.1.0",
+                "published_version": null,
+                "reason": "not-publishable",
+                "detail": "`publish = false`",
+            }),
+        ]
+    );
+}
+
+#[test]
+fn crates_for_other_registries_are_skipped() {
+    let fixture = Fixture::new(&[
+        (
+            "Cargo.toml",
+            r#"[workspace]
+members = ["mono_a", "mono_other"]
+"#,
+        ),
+        (
+            "mono_a/Cargo.toml",
+            r#"[package]
+name = "mono_a"
+version = "0.1.0"
+edition = "2018"
+description = "Fixture"
+license = "MIT"
+repository = "https://example.com"
+publish = ["crates-io"]
+"#,
+        ),
+        (
+            "mono_other/Cargo.toml",
+            r#"[package]
+name = "mono_other"
+version = "0.1.0"
+edition = "2018"
+description = "Fixture"
+license = "MIT"
+repository = "https://example.com"
+publish = ["other-registry"]
+"#,
+        ),
+    ]);
+
+    let output = fixture.run_ok(&["publish", "--no-verify", "--message-format", "json"]);
+    let events = events(&output);
+
+    assert_eq!(
+        events
+            .iter()
+            .filter(|e| e["event"] == "published")
+            .map(|e| e["package"].as_str().unwrap())
+            .collect::<Vec<_>>(),
+        ["mono_a"]
+    );
+    assert!(events.iter().any(|e| e["event"] == "skipped"
+        && e["package"] == "mono_other"
+        && e["detail"] == "not allowed to be published to crates.io"));
+}
+
+fn package(fixture: &Fixture, name: &str) -> Package {
+    MetadataCommand::new()
+        .current_dir(fixture.ws())
+        .no_deps()
+        .exec()
+        .unwrap()
+        .packages
+        .into_iter()
+        .find(|p| p.name == name)
+        .unwrap()
+}
+
+/// Publishes `mono_a` to the local registry and compares the checksum in the
+/// index with `mono_a` packaged again.
+async fn is_uploaded_after_publish(fixture: &Fixture) -> bool {
+    let registry = SparseIndex::new(&LocalRegistry::new(&fixture.registry()).unwrap().index_url());
+
+    is_uploaded_with_same_checksum(
+        &registry,
+        &package(fixture, "mono_a"),
+        &fixture.ws().join("target"),
+    )
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn uploaded_crate_with_same_checksum() {
+    let fixture = fixture();
+    fixture.run_ok(&["publish", "mono_a", "--no-verify"]);
+
+    assert!(is_uploaded_after_publish(&fixture).await);
+}
+
+#[tokio::test]
+async fn uploaded_crate_with_different_checksum() {
+    let fixture = fixture();
+    fixture.run_ok(&["publish", "mono_a", "--no-verify"]);
+    std::fs::write(
+        fixture.ws().join("mono_a/src/lib.rs"),
+        "pub fn changed() {}\n",
+    )
+    .unwrap();
+
+    assert!(!is_uploaded_after_publish(&fixture).await);
+}
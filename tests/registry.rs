@@ -0,0 +1,211 @@
+mod common;
+
+use cargo_metadata::{MetadataCommand, Package};
+use cargo_mono::{
+    bump::public_dependants,
+    plan::BumpPlan,
+    registry::{FakeRegistry, GitTags, Registry},
+};
+use common::Fixture;
+use semver::Version;
+
+fn v(s: &str) -> Version {
+    Version::parse(s).unwrap()
+}
+
+fn packages(fixture: &Fixture) -> Vec<Package> {
+    MetadataCommand::new()
+        .current_dir(fixture.ws())
+        .no_deps()
+        .exec()
+        .unwrap()
+        .packages
+}
+
+#[tokio::test]
+async fn plan_from_fake_registry() {
+    let fixture = Fixture::new(&[
+        (
+            "Cargo.toml",
+            r#"[workspace]
+members = ["mono_a", "mono_b"]
+"#,
+        ),
+        (
+            "mono_a/Cargo.toml",
+            r#"[package]
+name = "mono_a"
+version = "1.0.0"
+edition = "2018"
+"#,
+        ),
+        (
+            "mono_b/Cargo.toml",
+            r#"[package]
+name = "mono_b"
+version = "0.1.0"
+edition = "2018"
+
+[dependencies]
+mono_a = { version = "1.0.0", path = "../mono_a" }
+"#,
+        ),
+    ]);
+    let registry = FakeRegistry::default();
+    registry.publish("mono_a", v("1.2.3"), None);
+    registry.publish("mono_b", v("0.1.0"), None);
+
+    let mut plan = BumpPlan::default();
+    public_dependants(
+        &registry,
+        false,
+        &mut plan,
+        &packages(&fixture),
+        "mono_a",
+        true,
+        false,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(plan.crates["mono_a"].version, v("2.0.0"));
+    assert_eq!(plan.crates["mono_a"].dependants, ["mono_b"]);
+    assert_eq!(plan.crates["mono_b"].version, v("0.2.0"));
+}
+
+#[tokio::test]
+async fn merged_plan_keeps_most_severe_bump() {
+    let fixture = Fixture::new(&[
+        (
+            "Cargo.toml",
+            r#"[workspace]
+members = ["mono_x", "mono_y", "mono_shared"]
+"#,
+        ),
+        (
+            "mono_x/Cargo.toml",
+            r#"[package]
+name = "mono_x"
+version = "0.1.0"
+edition = "2018"
+"#,
+        ),
+        (
+            "mono_y/Cargo.toml",
+            r#"[package]
+name = "mono_y"
+version = "0.1.0"
+edition = "2018"
+"#,
+        ),
+        (
+            "mono_shared/Cargo.toml",
+            r#"[package]
+name = "mono_shared"
+version = "0.1.0"
+edition = "2018"
+
+[dependencies]
+mono_x = { version = "0.1.0", path = "../mono_x" }
+mono_y = { version = "0.1.0", path = "../mono_y" }
+"#,
+        ),
+    ]);
+    let packages = packages(&fixture);
+    let registry = FakeRegistry::default();
+    for name in &["mono_x", "mono_y", "mono_shared"] {
+        registry.publish(name, v("0.1.0"), None);
+    }
+
+    let plan_of = |name: &'static str, breaking: bool| {
+        let registry = &registry;
+        let packages = &packages;
+        async move {
+            let mut plan = BumpPlan::default();
+            public_dependants(registry, false, &mut plan, packages, name, breaking, true)
+                .await
+                .unwrap();
+            plan
+        }
+    };
+
+    // The order of crates should not matter.
+    for breaking_first in &[true, false] {
+        let breaking = plan_of("mono_x", true).await;
+        let patch = plan_of("mono_y", false).await;
+
+        let mut plan = BumpPlan::default();
+        if *breaking_first {
+            plan.merge(breaking);
+            plan.merge(patch);
+        } else {
+            plan.merge(patch);
+            plan.merge(breaking);
+        }
+
+        assert_eq!(plan.crates.len(), 3);
+        assert_eq!(plan.crates["mono_shared"].version, v("0.2.0"));
+        assert!(plan.crates["mono_shared"].breaking);
+        assert_eq!(plan.crates["mono_x"].version, v("0.2.0"));
+        assert_eq!(plan.crates["mono_y"].version, v("0.1.1"));
+    }
+}
+
+#[tokio::test]
+async fn fake_registry() {
+    let registry = FakeRegistry::default();
+    registry.publish("mono_a", v("0.1.0"), Some("abc".into()));
+    registry.publish("mono_a", v("0.2.0"), None);
+    registry.yank("mono_a", &v("0.2.0"));
+
+    assert_eq!(
+        registry.published_version("mono_a", false).await.unwrap(),
+        v("0.2.0")
+    );
+    assert!(registry.is_yanked("mono_a", &v("0.2.0")).await.unwrap());
+    assert_eq!(
+        registry
+            .fetch_checksum("mono_a", &v("0.1.0"))
+            .await
+            .unwrap(),
+        Some("abc".into())
+    );
+    assert_eq!(
+        registry.published_version("mono_b", true).await.unwrap(),
+        v("0.0.0")
+    );
+    assert!(registry.published_version("mono_b", false).await.is_err());
+}
+
+#[tokio::test]
+async fn versions_from_git_tags() {
+    let fixture = Fixture::new(&[("README.md", "")]);
+    fixture.git(&["init", "-q"]);
+    fixture.git(&["add", "."]);
+    fixture.git(&["commit", "-q", "-m", "init"]);
+    for tag in &[
+        "mono_a@0.1.0",
+        "mono_a-v0.3.0",
+        "mono_a/v0.2.0",
+        "mono_ab@9.0.0",
+        "mono_a@not-a-version",
+        "v10.0.0",
+    ] {
+        fixture.git(&["tag", tag]);
+    }
+
+    let registry = GitTags::new(&fixture.ws());
+
+    assert_eq!(
+        registry.published_version("mono_a", false).await.unwrap(),
+        v("0.3.0")
+    );
+    assert_eq!(
+        registry.published_version("mono_ab", false).await.unwrap(),
+        v("9.0.0")
+    );
+    assert_eq!(
+        registry.published_version("mono_c", true).await.unwrap(),
+        v("0.0.0")
+    );
+}